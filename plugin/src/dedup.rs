@@ -0,0 +1,183 @@
+//! Detection of entries that describe the same work under different keys, as happens when
+//! libraries from different sources are combined.
+
+use std::collections::HashMap;
+
+use hayagriva::Entry;
+use indexmap::IndexMap;
+
+use crate::model::{Merge, MergeReason};
+
+/// Finds the entries that duplicate an earlier entry. Two entries are considered the same work if
+/// they share a DOI, ISBN or arXiv identifier, or if they have the same title and year. ISBNs are
+/// only compared for works without a parent, since e.g. all chapters of a book share its ISBN.
+///
+/// Every duplicate is merged into the first entry of the work, whose key becomes the canonical
+/// key of all of them.
+pub fn find_duplicates(entries: &IndexMap<String, Entry>) -> Vec<Merge> {
+    let mut works = HashMap::new();
+    let mut merges = Vec::new();
+
+    for (key, entry) in entries {
+        let ids = identifiers(entry);
+        let canonical = ids.iter().find_map(|id| {
+            works
+                .get(id)
+                .map(|canonical: &String| (id.0, canonical.clone()))
+        });
+
+        let canonical = match canonical {
+            Some((reason, canonical)) => {
+                merges.push(Merge {
+                    key: key.clone(),
+                    into: canonical.clone(),
+                    reason,
+                });
+                canonical
+            }
+            None => key.clone(),
+        };
+        // also register the identifiers only this entry has, so that later entries sharing them
+        // are merged as well
+        for id in ids {
+            works.entry(id).or_insert_with(|| canonical.clone());
+        }
+    }
+
+    merges
+}
+
+/// The normalized identifiers of an entry, in the order they are matched.
+fn identifiers(entry: &Entry) -> Vec<(MergeReason, String)> {
+    let mut ids = Vec::new();
+
+    if let Some(doi) = entry.doi() {
+        let doi = doi.trim().to_lowercase();
+        let doi = ["https://doi.org/", "http://doi.org/", "doi:"]
+            .iter()
+            .find_map(|prefix| doi.strip_prefix(prefix))
+            .unwrap_or(&doi);
+        ids.push((MergeReason::Doi, doi.to_string()));
+    }
+    if entry.parents().is_empty() {
+        if let Some(isbn) = entry.isbn().and_then(normalize_isbn) {
+            ids.push((MergeReason::Isbn, isbn));
+        }
+    }
+    if let Some(arxiv) = entry.arxiv() {
+        let arxiv = arxiv.trim().to_lowercase();
+        let arxiv = arxiv.strip_prefix("arxiv:").unwrap_or(&arxiv);
+        // different versions of a preprint are still the same work
+        let arxiv = match arxiv.rsplit_once('v') {
+            Some((id, version)) if version.chars().all(|c| c.is_ascii_digit()) => id,
+            _ => arxiv,
+        };
+        ids.push((MergeReason::Arxiv, arxiv.to_string()));
+    }
+    if let (Some(title), Some(date)) = (entry.title(), entry.date()) {
+        let title = title
+            .value
+            .to_str()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !title.is_empty() {
+            ids.push((MergeReason::TitleYear, format!("{title} ({})", date.year)));
+        }
+    }
+
+    ids
+}
+
+/// Normalizes an ISBN to ISBN-13 without hyphens, so that the two forms of an ISBN match.
+fn normalize_isbn(isbn: &str) -> Option<String> {
+    let isbn = isbn
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect::<String>();
+
+    match isbn.len() {
+        13 => Some(isbn),
+        10 => {
+            let isbn = format!("978{}", &isbn[..9]);
+            let sum = isbn
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .enumerate()
+                .map(|(i, digit)| if i % 2 == 0 { digit } else { 3 * digit })
+                .sum::<u32>();
+            Some(format!("{isbn}{}", (10 - sum % 10) % 10))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_duplicates() {
+        let yaml = r#"
+            smith2020:
+              type: article
+              title: "At-scale impact of the Net Wok"
+              date: 2020
+              serial-number:
+                doi: 10.1000/NetWok
+            Smith_2020_Nature:
+              type: article
+              title: At-Scale Impact of the Net Wok!
+              date: 2020-05
+            wok-book:
+              type: book
+              title: The Wok Book
+              serial-number:
+                isbn: 0-306-40615-2
+            wok-book-2nd:
+              type: book
+              title: The Wok Book, Second Edition
+              serial-number:
+                isbn: 978-0-306-40615-7
+            preprint:
+              type: article
+              title: Dumplings
+              serial-number:
+                arxiv: 2001.01234v2
+                doi: https://doi.org/10.1000/netwok
+        "#;
+        let library = hayagriva::io::from_yaml_str(yaml).unwrap();
+        let entries = library
+            .into_iter()
+            .map(|entry| (entry.key().to_string(), entry))
+            .collect();
+
+        let merges = find_duplicates(&entries)
+            .into_iter()
+            .map(|merge| (merge.key, merge.into, merge.reason))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            merges,
+            [
+                (
+                    "Smith_2020_Nature".to_string(),
+                    "smith2020".to_string(),
+                    MergeReason::TitleYear
+                ),
+                (
+                    "wok-book-2nd".to_string(),
+                    "wok-book".to_string(),
+                    MergeReason::Isbn
+                ),
+                (
+                    "preprint".to_string(),
+                    "smith2020".to_string(),
+                    MergeReason::Doi
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+//! Errors and diagnostics reported to the Typst side. Besides a message for display, they carry
+//! structured information so that `lib.typ` can tell different problems apart.
+
+use std::fmt;
+use std::ops::Deref;
+
+use serde::Serialize;
+
+use crate::suggest;
+
+/// An error, boxed since errors are passed around a lot but rarely created.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Error(Box<ErrorData>);
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ErrorData {
+    pub kind: ErrorKind,
+    /// The complete message, including the location and suggestions.
+    pub message: String,
+    pub path: Option<String>,
+    /// One-based line of the error in the file at `path`, if known.
+    pub line: Option<usize>,
+    /// One-based column of the error in the file at `path`, if known.
+    pub column: Option<usize>,
+    /// The key of the entry or citation the error is about.
+    pub key: Option<String>,
+    pub suggestions: Vec<String>,
+    /// The field of the configuration that could not be decoded, e.g. `citations[3][0].form`.
+    pub field: Option<String>,
+    /// All errors, if several were found. The other fields describe the first one.
+    pub errors: Vec<Error>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// The configuration passed by `lib.typ` is invalid.
+    Config,
+    /// A bibliography file could not be decoded into text.
+    Decode,
+    /// The format of a bibliography file could not be determined.
+    UnknownFormat,
+    /// A bibliography file or the inline entries could not be parsed.
+    Parse,
+    DuplicateKey,
+    MissingKey,
+    UnknownStyle,
+    /// The locator of a citation is not one of CSL's locator types.
+    UnknownLocator,
+    /// A custom style could not be used.
+    InvalidStyle,
+    /// Rendering the bibliography failed.
+    Render,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self(Box::new(ErrorData {
+            kind,
+            message: message.into(),
+            path: None,
+            line: None,
+            column: None,
+            key: None,
+            suggestions: Vec::new(),
+            field: None,
+            errors: Vec::new(),
+        }))
+    }
+
+    /// Combines several errors into one that describes the first and lists all of them.
+    pub fn combine(mut errors: Vec<Error>) -> Option<Self> {
+        let mut combined = match errors.len() {
+            0 => return None,
+            1 => return errors.pop(),
+            _ => errors[0].clone(),
+        };
+        combined.0.message = match errors.len() - 1 {
+            1 => format!("{} and 1 more error", combined.message),
+            more => format!("{} and {more} more errors", combined.message),
+        };
+        combined.0.errors = errors;
+        Some(combined)
+    }
+
+    pub fn with_path(mut self, path: Option<&str>, location: Option<(usize, usize)>) -> Self {
+        self.0.path = path.map(str::to_string);
+        (self.0.line, self.0.column) = location.unzip();
+        self
+    }
+
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.0.key = Some(key.to_string());
+        self
+    }
+
+    /// Adds suggestions, which are also mentioned in the message.
+    pub fn with_suggestions(mut self, suggestions: Vec<String>) -> Self {
+        self.0
+            .message
+            .push_str(&suggest::did_you_mean(&suggestions));
+        self.0.suggestions = suggestions;
+        self
+    }
+
+    pub fn with_field(mut self, field: String) -> Self {
+        self.0.field = Some(field);
+        self
+    }
+}
+
+impl Deref for Error {
+    type Target = ErrorData;
+
+    fn deref(&self) -> &ErrorData {
+        &self.0
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A problem that did not prevent reading the bibliography.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The key of the entry the diagnostic is about.
+    pub key: Option<String>,
+    pub path: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Severity {
+    /// Something was left out of the bibliography, e.g. an invalid entry.
+    Error,
+    /// Something was ignored or replaced, e.g. an unknown field or a missing locale.
+    Warning,
+}
+
+impl Diagnostic {
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message: message.into(),
+            key: None,
+            path: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: Option<&str>, location: Option<(usize, usize)>) -> Self {
+        self.path = path.map(str::to_string);
+        (self.line, self.column) = location.unzip();
+        self
+    }
+
+    pub fn with_key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+}
+
+impl From<Error> for Diagnostic {
+    fn from(error: Error) -> Self {
+        let ErrorData {
+            message,
+            path,
+            line,
+            column,
+            key,
+            ..
+        } = *error.0;
+        Self {
+            severity: Severity::Error,
+            message,
+            key,
+            path,
+            line,
+            column,
+        }
+    }
+}
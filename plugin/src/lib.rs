@@ -2,21 +2,29 @@
 // https://github.com/typst/typst/blob/26e65bfef5b1da7f6c72e1409237cf03fb5d6069/crates/typst-library/src/model/bibliography.rs
 // licensed from the authors under Apache License 2.0
 
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::LazyLock;
 
 use hayagriva::{
     archive::ArchivedStyle, citationberg, BibliographyDriver, BibliographyRequest, CitationItem,
-    CitationRequest,
+    CitationRequest, ElemChild, ElemChildren, ElemMeta, Formatted, Formatting,
 };
 use indexmap::{map, IndexMap};
 use typed_arena::Arena;
 #[cfg(target_arch = "wasm32")]
 use wasm_minimal_protocol::wasm_func;
 
+mod dedup;
+mod error;
+mod lint;
+mod locator;
 mod model;
 mod read;
+mod suggest;
 mod util;
 
+use error::{Diagnostic, Error, ErrorKind};
 use model::*;
 use util::*;
 
@@ -35,143 +43,583 @@ pub fn names() -> Result<Vec<u8>, String> {
 
 static LOCALES: LazyLock<Vec<citationberg::Locale>> = LazyLock::new(hayagriva::archive::locales);
 
+/// An entry that is cited, hidden, at the end of the citation before one where other footnotes came
+/// in between, so that hayagriva doesn't consider them adjacent. It is only cited in a second pass
+/// that renders the citations after such breaks, so it has no effect on the bibliography. Its
+/// author and title make sure that it is sorted last, so that it is the preceding item.
+static NOTE_BREAK: LazyLock<hayagriva::Entry> = LazyLock::new(|| {
+    const LAST: &str = "\u{10FFFF}";
+    // the space makes sure that the key doesn't clash with any entry's
+    let mut entry = hayagriva::Entry::new(" note-break", hayagriva::types::EntryType::Misc);
+    entry.set_title(LAST.to_string().into());
+    let author = hayagriva::types::Person::from_strings(vec![LAST]).expect("name is not empty");
+    entry.set_authors(vec![author]);
+    entry
+});
+
 #[cfg_attr(target_arch = "wasm32", wasm_func)]
 pub fn read(config: &[u8]) -> Result<Vec<u8>, String> {
-    let config: Config = ciborium::from_reader(config).map_err_to_string()?;
-    let bibliography = read_impl(config)?;
-    let output = cbor_encode(&bibliography).map_err_to_string()?;
+    // errors are returned as data, so that the Typst side can handle them
+    let response = match decode_config(config).and_then(read_impl) {
+        Ok(bibliography) => Response::Ok(bibliography),
+        Err(error) => Response::Error(error),
+    };
+    let output = cbor_encode(&response).map_err_to_string()?;
+    Ok(output)
+}
+
+/// Checks the cited entries against the variables the style prints, without rendering them.
+#[cfg_attr(target_arch = "wasm32", wasm_func)]
+pub fn lint(config: &[u8]) -> Result<Vec<u8>, String> {
+    let response = match decode_config(config).and_then(lint_impl) {
+        Ok(diagnostics) => Response::Ok(diagnostics),
+        Err(error) => Response::Error(error),
+    };
+    let output = cbor_encode(&response).map_err_to_string()?;
     Ok(output)
 }
 
-fn read_libraries(sources: &[Resource]) -> Result<IndexMap<String, hayagriva::Entry>, String> {
-    let mut map = IndexMap::new();
-    let mut duplicates = Vec::new();
+fn decode_config(config: &[u8]) -> Result<Config, Error> {
+    /// The configuration, or an error naming the field that could not be decoded.
+    struct Tracked(Result<Config, Error>);
 
-    // We might have multiple bib/yaml files
-    for source in sources {
-        let library = read::decode_library(source)?;
+    impl<'de> serde::Deserialize<'de> for Tracked {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let config = serde_path_to_error::deserialize(deserializer).map_err(|err| {
+                let field = err.path().to_string();
+                let message = format!("invalid configuration at `{field}`: {}", err.inner());
+                Error::new(ErrorKind::Config, message).with_field(field)
+            });
+            Ok(Self(config))
+        }
+    }
 
-        for entry in library {
-            match map.entry(entry.key().into()) {
+    let Tracked(config) = ciborium::from_reader(config)
+        .map_err(|err| Error::new(ErrorKind::Config, format!("invalid configuration: {err}")))?;
+    config
+}
+
+/// The entries read from all sources of a bibliography.
+#[derive(Debug, Default)]
+struct Entries {
+    entries: IndexMap<String, hayagriva::Entry>,
+    /// Additional keys by which entries can be cited, mapped to the entries' keys.
+    aliases: IndexMap<String, String>,
+    diagnostics: Vec<Diagnostic>,
+    /// Where the entries are defined, as far as known.
+    locations: HashMap<String, Location>,
+}
+
+/// The file an entry is defined in, and its line and column in it.
+#[derive(Debug, Default)]
+struct Location {
+    path: Option<String>,
+    position: Option<(usize, usize)>,
+}
+
+impl Entries {
+    /// Looks up an entry by its key or one of its aliases, optionally ignoring case.
+    fn get(&self, key: &str, case_insensitive: bool) -> Option<&hayagriva::Entry> {
+        let entry = self
+            .entries
+            .get(key)
+            .or_else(|| self.entries.get(self.aliases.get(key)?));
+        if entry.is_some() || !case_insensitive {
+            return entry;
+        }
+
+        let key = key.to_lowercase();
+        let key = self
+            .entries
+            .keys()
+            .find(|other| other.to_lowercase() == key)
+            .or_else(|| {
+                let (_, target) = self
+                    .aliases
+                    .iter()
+                    .find(|(alias, _)| alias.to_lowercase() == key)?;
+                Some(target)
+            })?;
+        self.entries.get(key)
+    }
+
+    /// The aliases of the entry with the given key.
+    fn aliases_of<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a String> + 'a {
+        self.aliases
+            .iter()
+            .filter(move |(_, target)| *target == key)
+            .map(|(alias, _)| alias)
+    }
+}
+
+fn read_libraries(
+    sources: &[Source],
+    doi_keys: &HashMap<String, String>,
+    duplicates: Duplicates,
+    skip_invalid: bool,
+) -> Result<Entries, Error> {
+    let libraries = read::decode_libraries(sources, doi_keys, skip_invalid)?;
+
+    // in namespace mode, keys that appear in more than one source are renamed in all of them
+    let mut shared = HashSet::new();
+    if duplicates == Duplicates::Namespace {
+        let mut seen = HashSet::new();
+        for key in libraries.iter().flat_map(|decoded| decoded.library.keys()) {
+            if !seen.insert(key) {
+                shared.insert(key.to_string());
+            }
+        }
+    }
+
+    let mut result = Entries::default();
+    let Entries {
+        entries: map,
+        aliases,
+        diagnostics,
+        locations,
+    } = &mut result;
+    let mut origins = HashMap::new();
+    let mut conflicts = Vec::new();
+
+    // We might have multiple bib/yaml files, and entries given directly
+    for (i, decoded) in libraries.into_iter().enumerate() {
+        // the keys of the entries of this source that were kept, after renaming them
+        let mut kept = HashMap::new();
+        let path = match &sources[i] {
+            Source::Resource(resource) => resource.path.clone(),
+            Source::Entries(_) => None,
+        };
+        let locate = |key: &str| Location {
+            path: path.clone(),
+            position: decoded.locations.get(key).copied(),
+        };
+        diagnostics.extend(decoded.skipped.into_iter().map(Diagnostic::from));
+        diagnostics.extend(decoded.diagnostics);
+
+        for mut entry in decoded.library {
+            let original = entry.key().to_string();
+            if shared.contains(entry.key()) {
+                let key = format!("{}:{}", namespace(sources, i), entry.key());
+                let message = format!(
+                    "duplicate key `{}` from {} was renamed to `{key}`",
+                    entry.key(),
+                    describe(sources, i),
+                );
+                diagnostics.push(Diagnostic::warning(message).with_key(entry.key()));
+                entry = read::rekey(&entry, &key)?;
+            }
+
+            match map.entry(entry.key().to_string()) {
                 map::Entry::Vacant(vacant) => {
+                    origins.insert(entry.key().to_string(), i);
+                    locations.insert(entry.key().to_string(), locate(&original));
+                    kept.insert(original, entry.key().to_string());
                     vacant.insert(entry);
                 }
-                map::Entry::Occupied(_) => {
-                    duplicates.push(entry.key().to_string());
+                map::Entry::Occupied(mut occupied) => match duplicates {
+                    Duplicates::Error | Duplicates::Namespace => {
+                        conflicts.push(entry.key().to_string());
+                    }
+                    Duplicates::FirstWins => {
+                        let message = format!(
+                            "duplicate key `{}`: the entry from {} was ignored",
+                            entry.key(),
+                            describe(sources, i),
+                        );
+                        diagnostics.push(Diagnostic::warning(message).with_key(entry.key()));
+                    }
+                    Duplicates::LastWins => {
+                        let origin = origins.insert(entry.key().to_string(), i);
+                        let origin = origin.expect("existing entries have an origin");
+                        let message = format!(
+                            "duplicate key `{}`: the entry from {} was replaced by the one from {}",
+                            entry.key(),
+                            describe(sources, origin),
+                            describe(sources, i),
+                        );
+                        diagnostics.push(Diagnostic::warning(message).with_key(entry.key()));
+                        locations.insert(entry.key().to_string(), locate(&original));
+                        kept.insert(original, entry.key().to_string());
+                        occupied.insert(entry);
+                    }
+                },
+            }
+        }
+
+        for (alias, key) in decoded.aliases {
+            let Some(key) = kept.get(&key) else {
+                continue;
+            };
+            match aliases.entry(alias) {
+                map::Entry::Vacant(vacant) => {
+                    vacant.insert(key.clone());
+                }
+                map::Entry::Occupied(occupied) => {
+                    let message = format!(
+                        "alias `{}` of `{key}` was ignored, since it is already an alias of `{}`",
+                        occupied.key(),
+                        occupied.get(),
+                    );
+                    diagnostics.push(Diagnostic::warning(message).with_key(key));
                 }
             }
         }
     }
 
-    if !duplicates.is_empty() {
-        return Err(format!(
-            "duplicate bibliography keys: {}",
-            duplicates.join(", ")
-        ));
+    if !conflicts.is_empty() {
+        let message = format!("duplicate bibliography keys: {}", conflicts.join(", "));
+        return Err(Error::new(ErrorKind::DuplicateKey, message).with_key(&conflicts[0]));
+    }
+
+    // an entry's own key takes precedence over aliases of other entries
+    aliases.retain(|alias, key| {
+        let collides = map.contains_key(alias);
+        if collides {
+            let message = format!(
+                "alias `{alias}` of `{key}` was ignored, since it is already the key of an entry"
+            );
+            diagnostics.push(Diagnostic::warning(message).with_key(key));
+        }
+        !collides
+    });
+
+    Ok(result)
+}
+
+/// The namespace of the keys of a source: the name of its file without extension, or its position
+/// if it has no path.
+fn namespace(sources: &[Source], index: usize) -> String {
+    match &sources[index] {
+        Source::Resource(Resource {
+            path: Some(path), ..
+        }) => Path::new(path)
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.clone()),
+        _ => format!("source-{}", index + 1),
     }
+}
 
-    Ok(map)
+/// A description of a source for messages.
+fn describe(sources: &[Source], index: usize) -> String {
+    match &sources[index] {
+        Source::Resource(Resource {
+            path: Some(path), ..
+        }) => format!("`{path}`"),
+        _ => format!("source {}", index + 1),
+    }
 }
 
-fn read_impl(config: Config) -> Result<Bibliography, String> {
-    let entries = read_libraries(&config.sources)?;
+/// Reads the entries of all sources, and merges those describing the same work if requested.
+fn read_entries(config: &Config) -> Result<(Entries, Vec<Merge>), Error> {
+    let mut entries = read_libraries(
+        &config.sources,
+        &config.doi_keys,
+        config.duplicates,
+        config.skip_invalid,
+    )?;
 
-    let style = match config.style {
-        Style::BuiltIn(name) => ArchivedStyle::by_name(&name)
-            .ok_or(format!("Unknown style: {}", name))?
-            .get(),
-        Style::Custom(source) => citationberg::Style::from_xml(&source).map_err_to_string()?,
+    let merges = if config.deduplicate {
+        dedup::find_duplicates(&entries.entries)
+    } else {
+        Vec::new()
+    };
+    // merged entries can still be cited by their own keys and aliases
+    for merge in &merges {
+        entries.entries.shift_remove(&merge.key);
+        for target in entries.aliases.values_mut() {
+            if *target == merge.key {
+                target.clone_from(&merge.into);
+            }
+        }
+        entries
+            .aliases
+            .insert(merge.key.clone(), merge.into.clone());
+    }
+
+    Ok((entries, merges))
+}
+
+fn load_style(style: &Style) -> Result<citationberg::IndependentStyle, Error> {
+    let style = match style {
+        Style::BuiltIn(name) => style_by_name(name)?.get(),
+        Style::Custom(source) => citationberg::Style::from_xml(source)
+            .map_err(|err| Error::new(ErrorKind::InvalidStyle, format!("invalid style: {err}")))?,
     };
     let citationberg::Style::Independent(style) = style else {
-        return Err("style is not an IndependentStyle".to_string());
+        return Err(not_independent());
     };
+    Ok(style)
+}
+
+fn lint_impl(config: Config) -> Result<Vec<Diagnostic>, Error> {
+    let (entries, _) = read_entries(&config)?;
+    let style = load_style(&config.style)?;
+    Ok(lint_entries(&config, &entries, &style))
+}
+
+/// Reports the variables the style prints for the cited entries, but which they don't have.
+fn lint_entries(
+    config: &Config,
+    entries: &Entries,
+    style: &citationberg::IndependentStyle,
+) -> Vec<Diagnostic> {
+    let mut cited = IndexMap::new();
+    for citation in config.citations.iter().flatten() {
+        if let Some(entry) = entries.get(&citation.key, config.case_insensitive_keys) {
+            cited.insert(entry.key(), entry);
+        }
+    }
+    for key in &config.nocite {
+        if let Some(entry) = entries.get(key, config.case_insensitive_keys) {
+            cited.insert(entry.key(), entry);
+        }
+    }
+    if config.full {
+        cited.extend(entries.entries.values().map(|entry| (entry.key(), entry)));
+    }
+    let cited = cited.into_values().collect::<Vec<_>>();
+
+    let missing = lint::missing_variables(style, &cited, config.locale.clone(), &LOCALES);
+    let mut diagnostics = Vec::new();
+    for (key, variables) in missing {
+        let location = entries.locations.get(&key);
+        for variable in variables {
+            let message = format!("entry `{key}` has no `{variable}`, which the style uses");
+            diagnostics.push(Diagnostic::warning(message).with_key(&key).with_path(
+                location.and_then(|location| location.path.as_deref()),
+                location.and_then(|location| location.position),
+            ));
+        }
+    }
+    diagnostics
+}
+
+fn read_impl(config: Config) -> Result<Bibliography, Error> {
+    let (mut entries, merges) = read_entries(&config)?;
+    // the keys citations used to refer to entries other than their own keys
+    let mut cited_aliases = IndexMap::new();
+
+    let style = load_style(&config.style)?;
+    if config.lint {
+        let diagnostics = lint_entries(&config, &entries, &style);
+        entries.diagnostics.extend(diagnostics);
+    }
+
+    // hayagriva renders terms in English for languages it has no locale for
+    let mut locales = vec![&config.locale];
+    locales.extend(
+        config
+            .citations
+            .iter()
+            .flatten()
+            .map(|citation| &citation.locale),
+    );
+    let mut checked = HashSet::new();
+    for locale in locales {
+        if checked.insert(locale) && !has_locale(locale) {
+            let message = format!("no locale is available for `{}`, English is used", locale.0);
+            entries.diagnostics.push(Diagnostic::warning(message));
+        }
+    }
 
     let styles = Arena::new();
-    let mut footnotes = Vec::with_capacity(config.citations.len());
-    let mut driver = BibliographyDriver::new();
+    let locator_values = Arena::new();
+    let mut slots = Vec::with_capacity(config.citations.len());
+    let mut missing = Vec::new();
+    let mut requests = Vec::with_capacity(config.citations.len());
+    let mut last_note = None;
+    // in the rare note styles with numbered entries, "ibid." is preferable to wrong numbers, so
+    // note breaks are ignored in them
+    let numbered = |style| {
+        let variable = citationberg::taxonomy::NumberVariable::CitationNumber;
+        lint::prints(style, variable.into())
+    };
+    let style_numbered = numbered(&style);
+    let mut citation_styles = HashMap::new();
     for group in config.citations {
         let mut items = Vec::with_capacity(group.len());
+        let mut indices = Vec::with_capacity(group.len());
+        let mut placeholders = Vec::new();
         let mut normal = true;
 
-        for citation in &group {
-            let Some(entry) = entries.get(&citation.key) else {
-                return Err(format!(
-                    "key `{}` does not exist in the bibliography",
-                    citation.key
-                ));
+        for (i, citation) in group.iter().enumerate() {
+            // comare with https://github.com/typst/typst/blob/v0.14.1/crates/typst-library/src/model/bibliography.rs#L705-L717
+            // `None` (hidden) and `Some(CiteForm::Normal)` are considered normal forms, as is the
+            // normal form without the author
+            normal &= matches!(
+                citation.form,
+                None | Some(CiteForm::Normal | CiteForm::SuppressAuthor)
+            );
+
+            let Some(entry) = entries.get(&citation.key, config.case_insensitive_keys) else {
+                if config.missing == Missing::Error {
+                    return Err(missing_key(&citation.key, &entries));
+                }
+                if !missing.contains(&citation.key) {
+                    missing.push(citation.key.clone());
+                }
+                let placeholder = format!("[{}?]", citation.key);
+                placeholders.push(affixed(&placeholder, citation));
+                continue;
             };
+            if citation.key != entry.key() {
+                cited_aliases.insert(citation.key.clone(), entry.key().to_string());
+            }
 
-            let locator = citation.supplement.clone().map(|supplement| {
-                hayagriva::SpecificLocator(
-                    citationberg::taxonomy::Locator::Custom,
-                    hayagriva::LocatorPayload::Transparent(hayagriva::TransparentLocator::new(
-                        supplement,
-                    )),
-                )
-            });
+            let locator = citation
+                .supplement
+                .as_ref()
+                .map(|supplement| locator::locator(supplement, &locator_values))
+                .transpose()?;
 
             items.push(CitationItem::new(
                 entry,
                 locator,
                 None,
                 citation.form.is_none(),
-                citation.form.unwrap_or(None),
+                citation.form.and_then(CiteForm::purpose),
             ));
-
-            // comare with https://github.com/typst/typst/blob/v0.14.1/crates/typst-library/src/model/bibliography.rs#L705-L717
-            // `None` (hidden) and `Some(None)` (normal) are considered normal forms
-            normal &= matches!(citation.form, None | Some(None));
+            indices.push(i);
         }
 
-        let first = group.into_iter().next().ok_or("empty cite group")?;
+        let suppressed = group
+            .iter()
+            .enumerate()
+            .filter(|(_, citation)| citation.form == Some(CiteForm::SuppressAuthor))
+            .map(|(i, citation)| (i, citation.key.clone()))
+            .collect();
+        let affixes = group
+            .iter()
+            .map(|citation| (citation.prefix.clone(), citation.suffix.clone()))
+            .collect();
+        let first = group
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::new(ErrorKind::Render, "empty cite group"))?;
 
-        let citation_style = first
-            .style
-            .as_ref()
-            .map(|style| {
-                let style =
-                    ArchivedStyle::by_name(style).ok_or(format!("Unknown style: {}", style))?;
-                let citationberg::Style::Independent(style) = style.get() else {
-                    return Err("style is not an IndependentStyle".to_string());
-                };
-                let style = styles.alloc(style);
-                Ok(&*style)
-            })
-            .transpose()?
-            .unwrap_or(&style);
+        let (citation_style, citation_numbered) = match &first.style {
+            Some(name) => match citation_styles.get(name) {
+                Some(&cached) => cached,
+                None => {
+                    let citationberg::Style::Independent(style) = style_by_name(name)?.get() else {
+                        return Err(not_independent());
+                    };
+                    let style = &*styles.alloc(style);
+                    let cached = (style, numbered(style));
+                    citation_styles.insert(name.clone(), cached);
+                    cached
+                }
+            },
+            None => (&style, style_numbered),
+        };
 
         let footnote = normal && citation_style.settings.class == citationberg::StyleClass::Note;
-        footnotes.push(footnote);
+        // only groups that become footnotes have note numbers
+        let note = first.note.filter(|_| footnote && !items.is_empty());
+        // if other footnotes, e.g. with citations from other bibliographies, came in between, the
+        // previous citation was not the immediately preceding one, so e.g. "ibid." doesn't apply
+        let note_break = match (last_note, note) {
+            (Some(last), Some(note)) => note != last && note != last + 1,
+            _ => false,
+        };
+        let note_break = note_break && !citation_numbered && !style_numbered;
+        last_note = note.or(last_note);
 
-        driver.citation(CitationRequest::new(
-            items,
-            citation_style,
-            Some(first.locale),
-            &LOCALES,
-            None,
-        ));
+        // a group consisting only of missing keys is not rendered by hayagriva at all
+        let rendered = (!items.is_empty()).then_some(indices);
+        slots.push(CitationSlot {
+            footnote,
+            rendered,
+            placeholders,
+            suppressed,
+            affixes,
+        });
+
+        if !items.is_empty() {
+            requests.push(Request {
+                items,
+                style: citation_style,
+                locale: first.locale,
+                note,
+                note_break,
+            });
+        }
     }
 
-    if config.full {
-        for entry in entries.values() {
-            footnotes.push(false);
-            driver.citation(CitationRequest::new(
-                vec![CitationItem::new(entry, None, None, true, None)],
-                &style,
-                Some(config.locale.clone()),
-                &LOCALES,
-                None,
-            ));
+    // entries that are in the bibliography without being cited in the document
+    let mut uncited = Vec::new();
+    for key in &config.nocite {
+        match entries.get(key, config.case_insensitive_keys) {
+            Some(entry) => uncited.push(entry),
+            None if config.missing == Missing::Error => return Err(missing_key(key, &entries)),
+            None => {
+                if !missing.contains(key) {
+                    missing.push(key.clone());
+                }
+            }
         }
     }
-    let rendered = driver.finish(BibliographyRequest {
+    if config.full {
+        uncited.extend(entries.entries.values());
+    }
+    for entry in uncited {
+        slots.push(CitationSlot {
+            footnote: false,
+            rendered: Some(vec![0]),
+            placeholders: Vec::new(),
+            suppressed: Vec::new(),
+            affixes: Vec::new(),
+        });
+        requests.push(Request {
+            items: vec![CitationItem::new(entry, None, None, true, None)],
+            style: &style,
+            locale: config.locale.clone(),
+            note: None,
+            note_break: false,
+        });
+    }
+
+    let bibliography = BibliographyRequest {
         style: &style,
-        locale: Some(config.locale),
+        locale: Some(config.locale.clone()),
         locale_files: &LOCALES,
-    });
+    };
+    let mut driver = BibliographyDriver::new();
+    for request in &requests {
+        driver.citation(request.to_citation(false));
+    }
+    let rendered = driver.finish(bibliography);
+    let mut rendered_citations = rendered.citations;
+
+    // the citations after note breaks are rendered in a second pass, in which the citations before
+    // them end with a hidden citation of another entry
+    if requests.iter().any(|request| request.note_break) {
+        let mut driver = BibliographyDriver::new();
+        for (i, request) in requests.iter().enumerate() {
+            let note_break = requests.get(i + 1).is_some_and(|next| next.note_break);
+            driver.citation(request.to_citation(note_break));
+        }
+        let bibliography = BibliographyRequest {
+            style: &style,
+            locale: Some(config.locale),
+            locale_files: &LOCALES,
+        };
+        let second = driver.finish(bibliography).citations;
+        for ((citation, second), request) in
+            rendered_citations.iter_mut().zip(second).zip(&requests)
+        {
+            if request.note_break {
+                *citation = second;
+            }
+        }
+    }
 
     let Some(rendered_bib) = rendered.bibliography else {
-        return Err("no bibliography".to_string());
+        return Err(Error::new(ErrorKind::Render, "no bibliography"));
     };
 
     let references = rendered_bib
@@ -183,12 +631,21 @@ fn read_impl(config: Config) -> Result<Bibliography, String> {
             let content = reference.content;
 
             let details = entries
+                .entries
                 .get(&key)
                 .cloned()
                 .expect("key has been found before but not anymore");
+            // citations link to the key they used, so all of them need to be available
+            let mut aliases = entries.aliases_of(&key).cloned().collect::<Vec<_>>();
+            for (alias, _) in cited_aliases.iter().filter(|(_, target)| **target == key) {
+                if !aliases.contains(alias) {
+                    aliases.push(alias.clone());
+                }
+            }
 
             RenderedReference {
                 key,
+                aliases,
                 first_field,
                 content,
                 details,
@@ -196,17 +653,57 @@ fn read_impl(config: Config) -> Result<Bibliography, String> {
         })
         .collect();
 
-    assert_eq!(rendered.citations.len(), footnotes.len());
-    let citations = rendered
-        .citations
+    let mut diagnostics = entries.diagnostics;
+    let mut rendered_citations = rendered_citations.into_iter();
+    let citations = slots
         .into_iter()
-        .zip(footnotes)
-        .map(|(item, footnote)| {
-            let content = item.citation;
+        .map(|slot| {
+            let mut content = match &slot.rendered {
+                Some(indices) => {
+                    let item = rendered_citations
+                        .next()
+                        .expect("every rendered slot has a citation");
+                    let mut content = item.citation;
+                    // refer to the citations of the whole group, including missing ones
+                    remap_items(&mut content, indices);
+                    content
+                }
+                None => ElemChildren(Vec::new()),
+            };
+            let positions = slot.suppressed.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+            let kept = suppress_authors(&mut content, &positions);
+            for (_, key) in slot.suppressed.iter().filter(|(i, _)| kept.contains(i)) {
+                let message = format!(
+                    "the style only prints the author of `{key}`, so it is cited with the author \
+                     despite the `suppress-author` form"
+                );
+                if !diagnostics
+                    .iter()
+                    .any(|diagnostic| diagnostic.message == message)
+                {
+                    diagnostics.push(Diagnostic::warning(message).with_key(key));
+                }
+            }
+            // the placeholders of missing citations already have their affixes
+            add_affixes(
+                &mut content,
+                &slot.affixes,
+                &slot.rendered.unwrap_or_default(),
+            );
+            for placeholder in slot.placeholders {
+                if !content.0.is_empty() {
+                    content.0.push(text(" "));
+                }
+                content.0.push(text(&placeholder));
+            }
 
-            RenderedCitation { footnote, content }
+            RenderedCitation {
+                footnote: slot.footnote,
+                content,
+            }
         })
         .collect();
+    assert!(rendered_citations.next().is_none());
 
     let hanging_indent = rendered_bib.hanging_indent;
 
@@ -214,6 +711,272 @@ fn read_impl(config: Config) -> Result<Bibliography, String> {
         references,
         citations,
         hanging_indent,
+        diagnostics,
+        merges,
+        missing,
+    })
+}
+
+/// The error for a citation of a key that is not in the bibliography.
+fn missing_key(key: &str, entries: &Entries) -> Error {
+    let message = format!("key `{key}` does not exist in the bibliography");
+    let suggestions = suggest::suggest_keys(key, &entries.entries);
+    Error::new(ErrorKind::MissingKey, message)
+        .with_key(key)
+        .with_suggestions(suggestions)
+}
+
+/// Whether there is a locale for the language of the given locale.
+fn has_locale(locale: &citationberg::LocaleCode) -> bool {
+    let fallback = locale.fallback();
+    LOCALES.iter().any(|file| match &file.lang {
+        Some(lang) => lang == locale || Some(lang) == fallback.as_ref(),
+        None => false,
+    })
+}
+
+/// Looks up a built-in style, suggesting similar names if it doesn't exist.
+fn style_by_name(name: &str) -> Result<ArchivedStyle, Error> {
+    ArchivedStyle::by_name(name).ok_or_else(|| {
+        let names = ArchivedStyle::all()
+            .iter()
+            .flat_map(|style| style.names().iter().copied());
+        let suggestions = suggest::suggest_names(name, names);
+        Error::new(ErrorKind::UnknownStyle, format!("Unknown style: {name}"))
+            .with_suggestions(suggestions)
+    })
+}
+
+fn not_independent() -> Error {
+    Error::new(ErrorKind::InvalidStyle, "style is not an IndependentStyle")
+}
+
+/// A citation group as it appears in the output.
+struct CitationSlot {
+    footnote: bool,
+    /// The positions within the group of the citations that were rendered by hayagriva, if any.
+    rendered: Option<Vec<usize>>,
+    /// The markers of citations that are missing from the bibliography, with their affixes.
+    placeholders: Vec<String>,
+    /// The positions and keys of the citations whose authors are suppressed.
+    suppressed: Vec<(usize, String)>,
+    /// The prefixes and suffixes of the citations of the group, by their positions.
+    affixes: Vec<(Option<String>, Option<String>)>,
+}
+
+/// A citation to be rendered by hayagriva, which is kept to render it in several passes.
+struct Request<'a> {
+    items: Vec<CitationItem<'a, hayagriva::Entry>>,
+    style: &'a citationberg::IndependentStyle,
+    locale: citationberg::LocaleCode,
+    note: Option<usize>,
+    /// Whether other footnotes came between this citation and the previous one.
+    note_break: bool,
+}
+
+impl<'a> Request<'a> {
+    /// The request for hayagriva, ending with a hidden citation of [`NOTE_BREAK`] if the next
+    /// citation is not adjacent to this one.
+    fn to_citation(&self, note_break: bool) -> CitationRequest<'a, hayagriva::Entry> {
+        let mut items = self.items.clone();
+        if note_break {
+            items.push(CitationItem::new(&*NOTE_BREAK, None, None, true, None));
+        }
+        CitationRequest::new(
+            items,
+            self.style,
+            Some(self.locale.clone()),
+            &LOCALES,
+            self.note,
+        )
+    }
+}
+
+/// Replaces the indices of the items hayagriva rendered by their positions in the citation group.
+fn remap_items(children: &mut ElemChildren, indices: &[usize]) {
+    for child in &mut children.0 {
+        match child {
+            ElemChild::Elem(elem) => {
+                if let Some(ElemMeta::Entry(i)) = &mut elem.meta {
+                    *i = indices[*i];
+                }
+                remap_items(&mut elem.children, indices);
+            }
+            ElemChild::Transparent { cite_idx, .. } => *cite_idx = indices[*cite_idx],
+            _ => {}
+        }
+    }
+}
+
+/// Removes the authors from the items hayagriva rendered for the citations at the given positions.
+/// Everything else, e.g. the locator or the year suffix used for disambiguation, is kept. Items
+/// that would be left empty, e.g. in styles that only print the author, keep their authors instead.
+/// Returns the positions of these items.
+fn suppress_authors(children: &mut ElemChildren, positions: &[usize]) -> Vec<usize> {
+    let mut kept = Vec::new();
+    for child in &mut children.0 {
+        let ElemChild::Elem(elem) = child else {
+            continue;
+        };
+        let Some(ElemMeta::Entry(position)) = elem.meta else {
+            continue;
+        };
+        if !positions.contains(&position) {
+            continue;
+        }
+        let mut suppressed = elem.children.clone();
+        remove_names(&mut suppressed);
+        if is_blank(&suppressed) {
+            kept.push(position);
+        } else {
+            elem.children = suppressed;
+        }
+    }
+    kept
+}
+
+/// Whether rendered content shows nothing but whitespace.
+fn is_blank(children: &ElemChildren) -> bool {
+    children.0.iter().all(|child| match child {
+        ElemChild::Text(text) => text.text.trim().is_empty(),
+        ElemChild::Elem(elem) => is_blank(&elem.children),
+        _ => false,
+    })
+}
+
+/// Removes the first names and the delimiter that separated them from what followed, or from what
+/// preceded them if nothing follows. Returns whether names were found.
+fn remove_names(children: &mut ElemChildren) -> bool {
+    let names = children.0.iter().position(
+        |child| matches!(child, ElemChild::Elem(elem) if elem.meta == Some(ElemMeta::Names)),
+    );
+    let Some(i) = names else {
+        return children.0.iter_mut().any(|child| match child {
+            ElemChild::Elem(elem) => remove_names(&mut elem.children),
+            _ => false,
+        });
+    };
+
+    children.0.remove(i);
+    let is_delimiter = |child: Option<&ElemChild>| matches!(child, Some(ElemChild::Text(text)) if !text.text.chars().any(char::is_alphanumeric));
+    if is_delimiter(children.0.get(i)) {
+        children.0.remove(i);
+    } else if i > 0 && is_delimiter(children.0.get(i - 1)) {
+        children.0.remove(i - 1);
+    }
+    true
+}
+
+/// Puts the prefixes and suffixes of citations around the items hayagriva rendered for them, inside
+/// the brackets of styles that put these around each item, as in IEEE's "[1]". The affixes of the
+/// given cited positions whose items were collapsed into others are put around all items.
+fn add_affixes(
+    children: &mut ElemChildren,
+    affixes: &[(Option<String>, Option<String>)],
+    cited: &[usize],
+) {
+    if affixes.iter().all(|affixes| *affixes == (None, None)) {
+        return;
+    }
+    let entry = |child: &ElemChild| match child {
+        ElemChild::Elem(elem) => match elem.meta {
+            Some(ElemMeta::Entry(i)) => Some(i),
+            _ => None,
+        },
+        _ => None,
+    };
+    let rendered = children.0.iter().filter_map(entry).collect::<HashSet<_>>();
+    let first = children.0.iter().position(|child| entry(child).is_some());
+    let last = children.0.iter().rposition(|child| entry(child).is_some());
+    let (collapsed_prefixes, collapsed_suffixes): (Vec<_>, Vec<_>) = affixes
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| cited.contains(i) && !rendered.contains(i))
+        .map(|(_, affixes)| affixes.clone())
+        .unzip();
+
+    let children = &mut children.0;
+    for (position, child) in std::mem::take(children).into_iter().enumerate() {
+        let (prefix, suffix) = entry(&child)
+            .and_then(|i| affixes.get(i).cloned())
+            .unwrap_or_default();
+        let mut prefixes = Vec::new();
+        if Some(position) == first {
+            prefixes.extend(collapsed_prefixes.iter().flatten().cloned());
+        }
+        prefixes.extend(prefix);
+        let mut suffixes = suffix.into_iter().collect::<Vec<_>>();
+        if Some(position) == last {
+            suffixes.extend(collapsed_suffixes.iter().flatten().cloned());
+        }
+        let prefixes = prefixes.iter().map(|prefix| text(&format_prefix(prefix)));
+        let suffixes = suffixes.iter().map(|suffix| text(&format_suffix(suffix)));
+
+        match child {
+            ElemChild::Elem(mut elem) if is_bracketed(&elem.children) => {
+                let items = &mut elem.children.0;
+                let close = items.pop().expect("bracketed items have a closing bracket");
+                items.splice(1..1, prefixes);
+                items.extend(suffixes);
+                items.push(close);
+                children.push(ElemChild::Elem(elem));
+            }
+            child => {
+                children.extend(prefixes);
+                children.push(child);
+                children.extend(suffixes);
+            }
+        }
+    }
+}
+
+/// Whether rendered content starts with an opening bracket and ends with a closing one.
+fn is_bracketed(children: &ElemChildren) -> bool {
+    let consists_of = |child: Option<&ElemChild>, brackets: &str| match child {
+        Some(ElemChild::Text(text)) => {
+            !text.text.is_empty() && text.text.chars().all(|c| brackets.contains(c))
+        }
+        _ => false,
+    };
+    children.0.len() >= 2
+        && consists_of(children.0.first(), "([{")
+        && consists_of(children.0.last(), ")]}")
+}
+
+/// A citation's text with its prefix and suffix.
+fn affixed(text: &str, citation: &Citation) -> String {
+    let prefix = citation.prefix.as_deref().map(format_prefix);
+    let suffix = citation.suffix.as_deref().map(format_suffix);
+    format!(
+        "{}{text}{}",
+        prefix.unwrap_or_default(),
+        suffix.unwrap_or_default()
+    )
+}
+
+/// Separates a prefix from the citation by a space, unless it ends with one.
+fn format_prefix(prefix: &str) -> String {
+    if prefix.ends_with(char::is_whitespace) {
+        prefix.to_string()
+    } else {
+        format!("{prefix} ")
+    }
+}
+
+/// Separates a suffix from the citation by a space, unless it starts with one or with
+/// punctuation, as in ", for a review".
+fn format_suffix(suffix: &str) -> String {
+    if suffix.starts_with(|c: char| c.is_whitespace() || c.is_ascii_punctuation()) {
+        suffix.to_string()
+    } else {
+        format!(" {suffix}")
+    }
+}
+
+fn text(text: &str) -> ElemChild {
+    ElemChild::Text(Formatted {
+        text: text.to_string(),
+        formatting: Formatting::default(),
     })
 }
 
@@ -221,6 +984,72 @@ fn read_impl(config: Config) -> Result<Bibliography, String> {
 mod tests {
     use super::*;
 
+    /// A configuration for the IEEE style in English, with the default options.
+    fn config(sources: Vec<Source>, citations: Vec<Vec<Citation>>) -> Config {
+        Config {
+            sources,
+            full: false,
+            style: Style::BuiltIn("ieee".to_string()),
+            locale: citationberg::LocaleCode::en_us(),
+            doi_keys: HashMap::new(),
+            duplicates: Duplicates::Error,
+            deduplicate: false,
+            case_insensitive_keys: false,
+            missing: Missing::Error,
+            skip_invalid: false,
+            lint: false,
+            nocite: Vec::new(),
+            citations,
+        }
+    }
+
+    /// A source read from a file, or given as bytes if there is no path.
+    fn resource(path: Option<&str>, data: &str) -> Source {
+        Source::Resource(Resource {
+            path: path.map(str::to_string),
+            data: data.as_bytes().to_vec(),
+            format: None,
+            encoding: None,
+        })
+    }
+
+    /// Entries given directly in Hayagriva's YAML format.
+    fn entries(yaml: &str) -> Source {
+        Source::Entries(InlineEntries {
+            entries: serde_yaml::from_str(yaml).unwrap(),
+        })
+    }
+
+    /// A normal citation in English, without supplement or affixes.
+    fn cite(key: &str) -> Citation {
+        Citation {
+            key: key.to_string(),
+            form: Some(CiteForm::Normal),
+            style: None,
+            supplement: None,
+            prefix: None,
+            suffix: None,
+            locale: citationberg::LocaleCode::en_us(),
+            note: None,
+        }
+    }
+
+    /// The rendered citations as plain text.
+    fn plain(bibliography: &Bibliography) -> Vec<String> {
+        bibliography
+            .citations
+            .iter()
+            .map(|citation| {
+                let mut text = String::new();
+                citation
+                    .content
+                    .write_buf(&mut text, hayagriva::BufWriteFormat::Plain)
+                    .unwrap();
+                text
+            })
+            .collect()
+    }
+
     #[test]
     fn test_process() {
         let bib = r#"
@@ -235,22 +1064,665 @@ mod tests {
         }
         "#;
         let bibliography = read_impl(Config {
-            sources: vec![Resource {
+            sources: vec![Source::Resource(Resource {
                 path: None,
-                data: bib.to_string(),
-            }],
+                data: bib.as_bytes().to_vec(),
+                format: None,
+                encoding: None,
+            })],
             full: true,
             style: Style::BuiltIn("ieee".to_string()),
             locale: citationberg::LocaleCode::en_us(),
+            doi_keys: HashMap::new(),
+            duplicates: Duplicates::Error,
+            deduplicate: false,
+            case_insensitive_keys: false,
+            missing: Missing::Error,
+            skip_invalid: false,
+            lint: false,
+            nocite: Vec::new(),
             citations: vec![vec![Citation {
                 key: "netwok".to_string(),
                 form: None,
                 style: None,
-                supplement: Some("[p. 1]".to_string()),
+                supplement: Some(Supplement::Text("[p. 1]".to_string())),
+                prefix: None,
+                suffix: None,
                 locale: citationberg::LocaleCode::en_us(),
+                note: None,
             }]],
         })
         .unwrap();
         cbor_encode(&bibliography).unwrap();
     }
+
+    #[test]
+    fn test_locale_fallback() {
+        let config = Config {
+            locale: citationberg::LocaleCode("tlh".to_string()),
+            ..config(
+                vec![entries("netwok:\n  type: article\n  title: Net Wok")],
+                vec![vec![cite("netwok")]],
+            )
+        };
+        let bibliography = read_impl(config).unwrap();
+        let messages = bibliography
+            .diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            ["no locale is available for `tlh`, English is used"]
+        );
+    }
+
+    #[test]
+    fn test_missing_keys() {
+        let config = |missing| Config {
+            missing,
+            ..config(
+                vec![entries("netwok:\n  type: article\n  title: Net Wok")],
+                vec![
+                    vec![cite("typo"), cite("netwok")],
+                    vec![cite("typo"), cite("other")],
+                ],
+            )
+        };
+
+        let err = read_impl(config(Missing::Error)).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingKey);
+        assert_eq!(err.key.as_deref(), Some("typo"));
+        assert_eq!(err.message, "key `typo` does not exist in the bibliography");
+
+        let mut misspelled = config(Missing::Error);
+        misspelled.citations = vec![vec![cite("netwk")]];
+        let err = read_impl(misspelled).unwrap_err();
+        assert_eq!(err.suggestions, ["netwok"]);
+        assert_eq!(
+            err.message,
+            "key `netwk` does not exist in the bibliography (did you mean `netwok`?)"
+        );
+
+        let bibliography = read_impl(config(Missing::Placeholder)).unwrap();
+        assert_eq!(bibliography.missing, ["typo", "other"]);
+        assert_eq!(bibliography.references.len(), 1);
+
+        let texts = |children: &ElemChildren| {
+            children
+                .0
+                .iter()
+                .filter_map(|child| match child {
+                    ElemChild::Text(formatted) => Some(formatted.text.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        let citations = &bibliography.citations;
+        assert_eq!(texts(&citations[1].content), ["[typo?]", " ", "[other?]"]);
+
+        // the rendered citation still refers to the second citation of its group
+        let ElemChild::Elem(elem) = &citations[0].content.0[0] else {
+            panic!("expected an element");
+        };
+        assert_eq!(elem.meta, Some(ElemMeta::Entry(1)));
+        assert_eq!(texts(&citations[0].content).last().unwrap(), "[typo?]");
+    }
+
+    #[test]
+    fn test_nocite() {
+        let yaml = "netwok:\n  type: article\n  title: Net Wok\n\
+                    dumplings:\n  type: article\n  title: Dumplings\n\
+                    wok-book:\n  type: book\n  title: The Wok Book";
+        let config = |missing, nocite: &[&str]| Config {
+            missing,
+            nocite: nocite.iter().map(|key| key.to_string()).collect(),
+            ..config(vec![entries(yaml)], vec![vec![cite("netwok")]])
+        };
+        let keys = |bibliography: &Bibliography| {
+            bibliography
+                .references
+                .iter()
+                .map(|reference| reference.key.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let bibliography = read_impl(config(Missing::Error, &["wok-book", "netwok"])).unwrap();
+        assert_eq!(keys(&bibliography), ["netwok", "wok-book"]);
+
+        let err = read_impl(config(Missing::Error, &["dumplngs"])).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingKey);
+        assert_eq!(err.suggestions, ["dumplings"]);
+
+        let bibliography = read_impl(config(Missing::Placeholder, &["dumplngs"])).unwrap();
+        assert_eq!(keys(&bibliography), ["netwok"]);
+        assert_eq!(bibliography.missing, ["dumplngs"]);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        use ciborium::cbor;
+
+        let value = cbor!({ "sources" => [], "full" => "yes" }).unwrap();
+        let mut config = Vec::new();
+        ciborium::into_writer(&value, &mut config).unwrap();
+
+        let err = decode_config(&config).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Config);
+        assert_eq!(err.field.as_deref(), Some("full"));
+        assert!(err.message.starts_with("invalid configuration at `full`: "));
+
+        // options passed under the wrong name are not ignored
+        let value = cbor!({ "sources" => [], "ful" => true }).unwrap();
+        let mut config = Vec::new();
+        ciborium::into_writer(&value, &mut config).unwrap();
+
+        let err = decode_config(&config).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Config);
+        assert!(err.message.contains("unknown field `ful`"));
+    }
+
+    #[test]
+    fn test_inline_entries() {
+        let memo = entries("memo:\n  type: report\n  title: Internal memo\n  date: 2024-05");
+        let interview = entries("interview:\n  type: misc\n  author: [Astley, Rick]");
+        let library = read_libraries(
+            &[memo.clone(), interview],
+            &HashMap::new(),
+            Duplicates::Error,
+            false,
+        )
+        .unwrap();
+        let keys = library.entries.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["memo", "interview"]);
+
+        // the same rules as for Hayagriva YAML apply
+        let invalid = entries("memo:\n  type: report\n  date: May 2024");
+        assert!(read_libraries(&[invalid], &HashMap::new(), Duplicates::Error, false).is_err());
+
+        let err = read_libraries(
+            &[memo.clone(), memo],
+            &HashMap::new(),
+            Duplicates::Error,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::DuplicateKey);
+        assert_eq!(err.message, "duplicate bibliography keys: memo");
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let source = |path, title: &str| {
+            resource(
+                Some(path),
+                &format!("smith2020:\n  type: article\n  title: {title}\n"),
+            )
+        };
+        let sources = [
+            source("personal.yml", "Mine"),
+            source("library/department.yml", "Theirs"),
+        ];
+        let read =
+            |duplicates| read_libraries(&sources, &HashMap::new(), duplicates, false).unwrap();
+        let title = |library: &Entries, key: &str| {
+            let entry = library.get(key, false).unwrap();
+            entry.title().unwrap().value.to_str().to_string()
+        };
+
+        let library = read(Duplicates::FirstWins);
+        assert_eq!(title(&library, "smith2020"), "Mine");
+        assert_eq!(library.diagnostics.len(), 1);
+        assert_eq!(library.diagnostics[0].key.as_deref(), Some("smith2020"));
+        assert_eq!(
+            library.diagnostics[0].message,
+            "duplicate key `smith2020`: the entry from `library/department.yml` was ignored"
+        );
+
+        let library = read(Duplicates::LastWins);
+        assert_eq!(title(&library, "smith2020"), "Theirs");
+        assert_eq!(library.diagnostics.len(), 1);
+
+        let library = read(Duplicates::Namespace);
+        let keys = library.entries.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["personal:smith2020", "department:smith2020"]);
+        assert_eq!(title(&library, "department:smith2020"), "Theirs");
+        assert_eq!(library.diagnostics.len(), 2);
+    }
+
+    #[test]
+    fn test_aliases() {
+        let bib = resource(
+            Some("library.bib"),
+            "@book{Astley2020, title = {Net Wok}, ids = {netwok, astley}}",
+        );
+        let yaml = entries("memo:\n  type: report\n  ids: [internal-memo]");
+        let library =
+            read_libraries(&[bib, yaml], &HashMap::new(), Duplicates::Error, false).unwrap();
+
+        let key = |key, case_insensitive| {
+            let entry = library.get(key, case_insensitive)?;
+            Some(entry.key().to_string())
+        };
+        assert_eq!(key("astley", false).as_deref(), Some("Astley2020"));
+        assert_eq!(key("internal-memo", false).as_deref(), Some("memo"));
+        assert_eq!(key("NetWok", false), None);
+        assert_eq!(key("NetWok", true).as_deref(), Some("Astley2020"));
+        assert_eq!(key("astley2020", true).as_deref(), Some("Astley2020"));
+
+        let aliases = library.aliases_of("Astley2020").collect::<Vec<_>>();
+        assert_eq!(aliases, ["netwok", "astley"]);
+    }
+
+    #[test]
+    fn test_locators() {
+        let german = citationberg::LocaleCode("de-DE".to_string());
+        let citation = |supplement| Citation {
+            supplement: Some(supplement),
+            locale: german.clone(),
+            ..cite("netwok")
+        };
+        let config = Config {
+            style: Style::BuiltIn("apa".to_string()),
+            locale: german.clone(),
+            ..config(
+                vec![entries(
+                    "netwok:\n  type: article\n  title: Net Wok\n  author: Astley, Rick\n  date: 2020",
+                )],
+                vec![
+                    vec![citation(Supplement::Text("p. 5".to_string()))],
+                    vec![citation(Supplement::Locator {
+                        locator: "chapter".to_string(),
+                        value: "2".to_string(),
+                    })],
+                ],
+            )
+        };
+        let bibliography = read_impl(config).unwrap();
+        assert_eq!(
+            plain(&bibliography),
+            ["(Astley, 2020, S. 5)", "(Astley, 2020, Kapitel 2)"]
+        );
+    }
+
+    #[test]
+    fn test_affixes() {
+        let citation = |key, prefix: Option<&str>, suffix: Option<&str>| Citation {
+            prefix: prefix.map(str::to_string),
+            suffix: suffix.map(str::to_string),
+            ..cite(key)
+        };
+        let yaml = "netwok:\n  type: article\n  title: Net Wok\n  author: Astley, Rick\n  date: 2020\n\
+                    dumplings:\n  type: article\n  title: Dumplings\n  author: Morris, Linda\n  date: 2021";
+        let config = Config {
+            style: Style::BuiltIn("apa".to_string()),
+            missing: Missing::Placeholder,
+            ..config(
+                vec![entries(yaml)],
+                vec![
+                    vec![citation("netwok", Some("see"), Some(", for a review"))],
+                    vec![
+                        citation("dumplings", None, Some("on dumplings")),
+                        citation("netwok", Some("e.g.,"), None),
+                    ],
+                    vec![citation("wok", Some("see"), None)],
+                    // the affixes of a missing citation only apply to its placeholder
+                    vec![
+                        citation("wok", Some("cf."), None),
+                        citation("netwok", None, None),
+                    ],
+                ],
+            )
+        };
+        let bibliography = read_impl(config.clone()).unwrap();
+        assert_eq!(
+            plain(&bibliography),
+            [
+                "(see Astley, 2020, for a review)",
+                "(e.g., Astley, 2020; Morris, 2021 on dumplings)",
+                "see [wok?]",
+                "(Astley, 2020) cf. [wok?]",
+            ]
+        );
+
+        // IEEE puts brackets around each item, and the affixes inside them
+        let ieee = Config {
+            citations: vec![
+                vec![citation("netwok", Some("see"), Some(", for a review"))],
+                vec![
+                    citation("dumplings", None, Some("on dumplings")),
+                    citation("netwok", Some("e.g.,"), None),
+                ],
+            ],
+            style: Style::BuiltIn("ieee".to_string()),
+            ..config
+        };
+        assert_eq!(
+            plain(&read_impl(ieee).unwrap()),
+            ["[see 1, for a review]", "[e.g., 1], [2 on dumplings]"]
+        );
+    }
+
+    #[test]
+    fn test_suppress_author() {
+        let citation = |key, form, supplement: Option<&str>| Citation {
+            form: Some(form),
+            supplement: supplement.map(|supplement| Supplement::Text(supplement.to_string())),
+            ..cite(key)
+        };
+        let yaml = "netwok:\n  type: article\n  title: Net Wok\n  author: Astley, Rick\n  date: 2020\n\
+                    dumplings:\n  type: article\n  title: Dumplings\n  author: Astley, Rick\n  date: 2020";
+        let config = |style: &str| Config {
+            style: Style::BuiltIn(style.to_string()),
+            ..config(
+                vec![entries(yaml)],
+                vec![
+                    vec![citation("netwok", CiteForm::SuppressAuthor, Some("p. 4"))],
+                    vec![citation("dumplings", CiteForm::SuppressAuthor, None)],
+                    vec![citation("dumplings", CiteForm::Normal, None)],
+                ],
+            )
+        };
+        let citations = |style| plain(&read_impl(config(style)).unwrap());
+        assert_eq!(
+            citations("apa"),
+            ["(2020a, p. 4)", "(2020b)", "(Astley, 2020b)"]
+        );
+        assert_eq!(citations("ieee"), ["[1, p. 4]", "[2]", "[2]"]);
+
+        // MLA only prints the author and the locator, so without a locator, the author is kept
+        let yaml = "netwok:\n  type: article\n  title: Net Wok\n  author: Astley, Rick\n\
+                    dumplings:\n  type: article\n  title: Dumplings\n  author: Morris, Linda";
+        let mla = Config {
+            sources: vec![entries(yaml)],
+            citations: vec![
+                vec![citation("netwok", CiteForm::SuppressAuthor, Some("p. 4"))],
+                vec![citation("netwok", CiteForm::SuppressAuthor, None)],
+                vec![
+                    citation("netwok", CiteForm::SuppressAuthor, None),
+                    citation("dumplings", CiteForm::Normal, None),
+                ],
+                vec![
+                    citation("dumplings", CiteForm::Normal, None),
+                    citation("netwok", CiteForm::SuppressAuthor, None),
+                ],
+            ],
+            ..config("modern-language-association")
+        };
+        let bibliography = read_impl(mla).unwrap();
+        assert_eq!(
+            plain(&bibliography),
+            ["(4)", "(Astley)", "(Astley; Morris)", "(Morris; Astley)"]
+        );
+        let warnings = bibliography
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.key.as_deref(), diagnostic.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [(
+                Some("netwok"),
+                "the style only prints the author of `netwok`, so it is cited with the author \
+                 despite the `suppress-author` form"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_note_numbers() {
+        let style = r#"<style xmlns="http://purl.org/net/xbiblio/csl" class="note" version="1.0">
+            <info><title>Notes</title><id>notes</id><updated>2024-01-01T00:00:00+00:00</updated></info>
+            <citation near-note-distance="2"><layout><choose>
+                <if position="ibid"><text value="ibid"/></if>
+                <else-if position="near-note">
+                    <group delimiter=" "><text value="see note"/><number variable="first-reference-note-number"/></group>
+                </else-if>
+                <else-if position="subsequent"><text value="subsequent"/></else-if>
+                <else><text variable="title"/></else>
+            </choose></layout></citation>
+            <bibliography><layout><text variable="title"/></layout></bibliography>
+        </style>"#;
+        let citation = |key, note| Citation { note, ..cite(key) };
+        let yaml = "netwok:\n  type: article\n  title: Net Wok\n  author: Astley, Rick\n  date: 2020\n\
+                    dumplings:\n  type: article\n  title: Dumplings\n  author: Morris, Linda\n  date: 2021";
+        let config = Config {
+            style: Style::Custom(style.to_string()),
+            ..config(
+                vec![entries(yaml)],
+                vec![
+                    vec![citation("netwok", Some(1))],
+                    vec![citation("netwok", Some(2))],
+                    vec![citation("dumplings", Some(3))],
+                    vec![citation("netwok", Some(4))],
+                    vec![citation("dumplings", None)],
+                ],
+            )
+        };
+        let citations = |config| {
+            let bibliography = read_impl(config).unwrap();
+            assert!(bibliography
+                .references
+                .iter()
+                .all(|reference| reference.key != NOTE_BREAK.key()));
+            plain(&bibliography)
+        };
+        assert_eq!(
+            citations(config.clone()),
+            ["Net Wok", "ibid", "Dumplings", "see note 1", "subsequent"]
+        );
+
+        // without note numbers, nothing is in a near note
+        let mut without_notes = config.clone();
+        for citation in without_notes.citations.iter_mut().flatten() {
+            citation.note = None;
+        }
+        assert_eq!(
+            citations(without_notes),
+            ["Net Wok", "ibid", "Dumplings", "subsequent", "subsequent"]
+        );
+
+        // another footnote, e.g. with a citation from another bibliography, came in between
+        let mut other_note = config.clone();
+        other_note.citations = vec![
+            vec![citation("netwok", Some(1))],
+            vec![citation("netwok", Some(3))],
+            vec![citation("netwok", Some(3))],
+        ];
+        assert_eq!(citations(other_note), ["Net Wok", "see note 1", "ibid"]);
+
+        // breaks between several notes, which are each in a near note of the previous one
+        let mut other_notes = config.clone();
+        other_notes.citations = vec![
+            vec![citation("netwok", Some(1))],
+            vec![citation("netwok", Some(3))],
+            vec![citation("netwok", Some(5))],
+        ];
+        assert_eq!(
+            citations(other_notes),
+            ["Net Wok", "see note 1", "see note 1"]
+        );
+
+        // the break doesn't take up a citation of the near-note distance
+        let mut distance = config.clone();
+        distance.citations = vec![
+            vec![citation("netwok", Some(1))],
+            vec![citation("dumplings", Some(3))],
+            vec![citation("netwok", Some(3))],
+        ];
+        assert_eq!(citations(distance), ["Net Wok", "Dumplings", "see note 1"]);
+
+        // the break comes after the last citation even if the style sorts the citations
+        let sorted_citations = style.replace(
+            "<citation near-note-distance=\"2\"><layout>",
+            r#"<macro name="title"><text variable="title"/></macro>
+            <citation near-note-distance="2"><sort><key macro="title"/></sort><layout delimiter="; ">"#,
+        );
+        let sorted_citations = Config {
+            style: Style::Custom(sorted_citations),
+            citations: vec![
+                vec![citation("netwok", Some(1)), citation("dumplings", Some(1))],
+                vec![citation("netwok", Some(3))],
+            ],
+            ..config.clone()
+        };
+        assert_eq!(
+            citations(sorted_citations),
+            ["Dumplings; Net Wok", "see note 1"]
+        );
+
+        // citations inside a footnote get the number of the next one, as the one following it
+        let mut same_note = config.clone();
+        same_note.citations = vec![
+            vec![citation("netwok", Some(2))],
+            vec![citation("netwok", Some(2))],
+        ];
+        assert_eq!(citations(same_note), ["Net Wok", "ibid"]);
+
+        // the hidden citation breaking "ibid." doesn't change the order of the bibliography
+        let sorted = style.replace(
+            "<bibliography>",
+            r#"<bibliography><sort><key variable="title"/></sort>"#,
+        );
+        let sorted = Config {
+            style: Style::Custom(sorted),
+            citations: vec![
+                vec![citation("netwok", Some(1))],
+                vec![citation("netwok", Some(3))],
+                vec![citation("dumplings", Some(4))],
+            ],
+            ..config.clone()
+        };
+        let bibliography = read_impl(sorted).unwrap();
+        assert_eq!(plain(&bibliography), ["Net Wok", "see note 1", "Dumplings"]);
+        let keys = (bibliography.references.iter())
+            .map(|reference| reference.key.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["dumplings", "netwok"]);
+
+        // nor does it take a citation number, since it is not cited in numbered styles
+        let numbered = style.replace(
+            r#"<else><text variable="title"/></else>"#,
+            r#"<else><text variable="citation-number"/></else>"#,
+        );
+        let numbered = Config {
+            style: Style::Custom(numbered),
+            citations: vec![
+                vec![citation("netwok", Some(1))],
+                vec![citation("netwok", Some(3))],
+                vec![citation("dumplings", Some(4))],
+            ],
+            ..config.clone()
+        };
+        assert_eq!(citations(numbered.clone()), ["1", "ibid", "2"]);
+        let mut adjacent = numbered;
+        for (i, group) in adjacent.citations.iter_mut().enumerate() {
+            group[0].note = Some(i + 1);
+        }
+        assert_eq!(citations(adjacent), ["1", "ibid", "2"]);
+
+        // in-text styles are not affected by note numbers
+        let ieee = Config {
+            style: Style::BuiltIn("ieee".to_string()),
+            citations: vec![
+                vec![citation("netwok", Some(1))],
+                vec![citation("dumplings", Some(3))],
+                vec![citation("netwok", Some(5))],
+            ],
+            ..config
+        };
+        assert_eq!(citations(ieee), ["[1]", "[2]", "[1]"]);
+    }
+
+    #[test]
+    fn test_lint() {
+        let bib = "@article{netwok,\n  title = {Net Wok},\n  journal = {Armenian Journal},\n  year = {2020}\n}\n\n@article{dumplings,\n  title = {Dumplings}\n}\n";
+        let config = Config {
+            full: true,
+            style: Style::BuiltIn("apa".to_string()),
+            ..config(vec![resource(Some("library.bib"), bib)], Vec::new())
+        };
+
+        let diagnostics = lint_impl(config.clone()).unwrap();
+        let problems = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let location = (diagnostic.path.as_deref(), diagnostic.line);
+                (diagnostic.message.as_str(), location)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            problems,
+            [
+                (
+                    "entry `dumplings` has no `container-title`, which the style uses",
+                    (Some("library.bib"), Some(7))
+                ),
+                (
+                    "entry `dumplings` has no `issued`, which the style uses",
+                    (Some("library.bib"), Some(7))
+                ),
+            ]
+        );
+
+        let bibliography = read_impl(Config {
+            lint: true,
+            ..config
+        })
+        .unwrap();
+        assert_eq!(bibliography.diagnostics, diagnostics);
+    }
+
+    #[test]
+    fn test_lint_function() {
+        use ciborium::cbor;
+
+        let bib = "@article{dumplings,\n  title = {Dumplings}\n}\n";
+        let value = cbor!({
+            "sources" => [{ "path" => "library.bib", "data" => bib }],
+            "full" => false,
+            "style" => { "built-in" => "apa" },
+            "locale" => "en-US",
+            "citations" => [[{ "key" => "dumplings", "form" => "normal", "locale" => "en-US" }]],
+        })
+        .unwrap();
+        let mut config = Vec::new();
+        ciborium::into_writer(&value, &mut config).unwrap();
+
+        let output = lint(&config).unwrap();
+        let response: ciborium::Value = ciborium::from_reader(output.as_slice()).unwrap();
+        let [(status, diagnostics)] = response.as_map().unwrap().as_slice() else {
+            panic!("the response is either `ok` or `error`");
+        };
+        assert_eq!(status.as_text(), Some("ok"));
+        let diagnostics = diagnostics.as_array().unwrap();
+        let keys_and_messages = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let field = |name: &str| {
+                    let (_, value) = diagnostic
+                        .as_map()
+                        .unwrap()
+                        .iter()
+                        .find(|(key, _)| key.as_text() == Some(name))
+                        .unwrap();
+                    value.as_text().unwrap().to_string()
+                };
+                (field("key"), field("message"))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            keys_and_messages,
+            [
+                (
+                    "dumplings".to_string(),
+                    "entry `dumplings` has no `container-title`, which the style uses".to_string()
+                ),
+                (
+                    "dumplings".to_string(),
+                    "entry `dumplings` has no `issued`, which the style uses".to_string()
+                ),
+            ]
+        );
+    }
 }
@@ -0,0 +1,377 @@
+//! Checks of the cited entries against the variables a CSL style prints, so that e.g. an entry
+//! without a date, which would be rendered as "n.d.", is noticed.
+//!
+//! Which variables a style prints for an entry depends on the entry's CSL type. Since Hayagriva
+//! doesn't expose how it maps entries to CSL types and variables, both are found out by rendering
+//! the entries with a probe style that tests for them.
+
+use std::collections::{HashMap, HashSet};
+
+use hayagriva::citationberg::taxonomy::{
+    DateVariable, Kind, NameVariable, StandardVariable, Variable,
+};
+use hayagriva::citationberg::{
+    self, ChooseBranch, ChooseMatch, IndependentStyle, LayoutRenderingElement, Locale, LocaleCode,
+    NamesChild, TextTarget,
+};
+use hayagriva::{
+    BibliographyDriver, BibliographyRequest, BufWriteFormat, CitationItem, CitationRequest, Entry,
+};
+use serde::Serialize;
+
+/// The variables whose absence is reported, since styles rely on them to identify a work.
+const CHECKED: [Variable; 4] = [
+    Variable::Standard(StandardVariable::Title),
+    Variable::Standard(StandardVariable::ContainerTitle),
+    Variable::Standard(StandardVariable::Publisher),
+    Variable::Date(DateVariable::Issued),
+];
+
+/// The variables a style prints for an entry but the entry doesn't have, by key of the entry.
+pub fn missing_variables(
+    style: &IndependentStyle,
+    entries: &[&Entry],
+    locale: LocaleCode,
+    locale_files: &[Locale],
+) -> Vec<(String, Vec<String>)> {
+    // first find out which types and variables the style tests for, and which names it requires
+    let mut usage = Usage::default();
+    Walk::new(style, None).layouts(&mut usage);
+    let variables = CHECKED
+        .iter()
+        .chain(&usage.tested)
+        .map(csl_name)
+        .chain(usage.names.iter().flatten().map(csl_name))
+        .collect::<HashSet<_>>();
+    let probed = probe(entries, &usage.kinds, &variables, locale, locale_files);
+
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let (kinds, present) = probed.get(entry.key())?;
+            let mut usage = Usage::default();
+            Walk::new(style, Some((kinds, present))).layouts(&mut usage);
+
+            let mut missing = CHECKED
+                .iter()
+                .filter(|variable| usage.printed.contains(variable))
+                .map(csl_name)
+                .filter(|variable| !present.contains(variable))
+                .collect::<Vec<_>>();
+            // if the style substitutes other names for missing authors, any of them will do
+            let authors = usage
+                .names
+                .iter()
+                .filter(|names| names.first() == Some(&NameVariable::Author));
+            for names in authors {
+                if !names.iter().any(|name| present.contains(&csl_name(name))) {
+                    missing.insert(0, csl_name(NameVariable::Author));
+                    break;
+                }
+            }
+
+            (!missing.is_empty()).then(|| (entry.key().to_string(), missing))
+        })
+        .collect()
+}
+
+/// Whether a style prints a variable in any of its citations or bibliography entries.
+pub fn prints(style: &IndependentStyle, variable: Variable) -> bool {
+    let mut usage = Usage::default();
+    Walk::new(style, None).layouts(&mut usage);
+    usage.printed.contains(&variable)
+}
+
+/// The types of the entries among the given ones, and which of the given variables they have.
+fn probe(
+    entries: &[&Entry],
+    kinds: &HashSet<Kind>,
+    variables: &HashSet<String>,
+    locale: LocaleCode,
+    locale_files: &[Locale],
+) -> HashMap<String, (HashSet<Kind>, HashSet<String>)> {
+    let kinds = kinds
+        .iter()
+        .map(|kind| (csl_name(kind), *kind))
+        .collect::<HashMap<_, _>>();
+
+    let mut layout = String::new();
+    for name in kinds.keys() {
+        layout.push_str(&format!(
+            r#"<choose><if type="{name}"><text value="type:{name};"/></if></choose>"#
+        ));
+    }
+    for name in variables {
+        layout.push_str(&format!(
+            r#"<choose><if variable="{name}"><text value="variable:{name};"/></if></choose>"#
+        ));
+    }
+    let xml = format!(
+        r#"<style xmlns="http://purl.org/net/xbiblio/csl" class="in-text" version="1.0">
+            <info><title>Probe</title><id>probe</id><updated>2024-01-01T00:00:00+00:00</updated></info>
+            <citation><layout><text value="probe"/></layout></citation>
+            <bibliography><layout>{layout}</layout></bibliography>
+        </style>"#
+    );
+    let Ok(citationberg::Style::Independent(style)) = citationberg::Style::from_xml(&xml) else {
+        unreachable!("the probe style is valid");
+    };
+
+    let mut driver = BibliographyDriver::new();
+    for entry in entries {
+        driver.citation(CitationRequest::new(
+            vec![CitationItem::new(*entry, None, None, true, None)],
+            &style,
+            Some(locale.clone()),
+            locale_files,
+            None,
+        ));
+    }
+    let rendered = driver.finish(BibliographyRequest {
+        style: &style,
+        locale: Some(locale),
+        locale_files,
+    });
+
+    let mut probed = HashMap::new();
+    for item in rendered.bibliography.into_iter().flat_map(|bib| bib.items) {
+        let mut output = String::new();
+        item.content
+            .write_buf(&mut output, BufWriteFormat::Plain)
+            .expect("writing to a string doesn't fail");
+
+        let mut item_kinds = HashSet::new();
+        let mut present = HashSet::new();
+        for token in output.split(';') {
+            if let Some(kind) = token.strip_prefix("type:").and_then(|name| kinds.get(name)) {
+                item_kinds.insert(*kind);
+            } else if let Some(variable) = token.strip_prefix("variable:") {
+                present.insert(variable.to_string());
+            }
+        }
+        probed.insert(item.key, (item_kinds, present));
+    }
+    probed
+}
+
+/// The name of a type or variable in CSL, which is not always what its `Display` implementation
+/// writes, e.g. for `archive-place`.
+fn csl_name(value: impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => unreachable!("types and variables are serialized as strings"),
+    }
+}
+
+/// What the parts of a style that apply to an entry use.
+#[derive(Default)]
+struct Usage {
+    /// The types the style tests for.
+    kinds: HashSet<Kind>,
+    /// The variables the style tests for.
+    tested: HashSet<Variable>,
+    /// The variables the style prints.
+    printed: HashSet<Variable>,
+    /// The name variables of `cs:names` elements that only substitute other names, so that one of
+    /// them must be present.
+    names: Vec<Vec<NameVariable>>,
+}
+
+/// A walk through the rendering elements of a style. If the types and variables of an entry are
+/// given, only the branches of conditions that can apply to it are visited.
+struct Walk<'a> {
+    style: &'a IndependentStyle,
+    entry: Option<(&'a HashSet<Kind>, &'a HashSet<String>)>,
+    /// The macros currently being visited, to guard against cycles.
+    macros: Vec<&'a str>,
+}
+
+impl<'a> Walk<'a> {
+    fn new(
+        style: &'a IndependentStyle,
+        entry: Option<(&'a HashSet<Kind>, &'a HashSet<String>)>,
+    ) -> Self {
+        Self {
+            style,
+            entry,
+            macros: Vec::new(),
+        }
+    }
+
+    fn layouts(&mut self, usage: &mut Usage) {
+        let style = self.style;
+        self.elements(&style.citation.layout.elements, usage);
+        if let Some(bibliography) = &style.bibliography {
+            self.elements(&bibliography.layout.elements, usage);
+        }
+    }
+
+    fn elements(&mut self, elements: &'a [LayoutRenderingElement], usage: &mut Usage) {
+        for element in elements {
+            self.element(element, usage);
+        }
+    }
+
+    fn element(&mut self, element: &'a LayoutRenderingElement, usage: &mut Usage) {
+        match element {
+            LayoutRenderingElement::Text(text) => match &text.target {
+                TextTarget::Variable { var, .. } => {
+                    usage.printed.insert(*var);
+                }
+                TextTarget::Macro { name } => {
+                    let Some(found) = self.style.macros.iter().find(|m| &m.name == name) else {
+                        return;
+                    };
+                    if self.macros.contains(&name.as_str()) {
+                        return;
+                    }
+                    self.macros.push(name);
+                    self.elements(&found.children, usage);
+                    self.macros.pop();
+                }
+                _ => {}
+            },
+            LayoutRenderingElement::Date(date) => {
+                if let Some(variable) = date.variable {
+                    usage.printed.insert(variable.into());
+                }
+            }
+            LayoutRenderingElement::Number(number) => {
+                usage.printed.insert(number.variable.into());
+            }
+            LayoutRenderingElement::Names(names) => {
+                let mut variables = names.variable.clone();
+                let mut only_names = true;
+                for child in &names.children {
+                    let NamesChild::Substitute(substitute) = child else {
+                        continue;
+                    };
+                    for element in &substitute.children {
+                        match element {
+                            LayoutRenderingElement::Names(names) => {
+                                variables.extend(&names.variable);
+                            }
+                            _ => only_names = false,
+                        }
+                    }
+                }
+                if only_names {
+                    usage.names.push(variables);
+                }
+                for variable in &names.variable {
+                    usage.printed.insert((*variable).into());
+                }
+            }
+            LayoutRenderingElement::Label(_) => {}
+            LayoutRenderingElement::Group(group) => self.elements(&group.children, usage),
+            LayoutRenderingElement::Choose(choose) => {
+                for branch in std::iter::once(&choose.if_).chain(&choose.else_if) {
+                    usage.kinds.extend(branch.type_.iter().flatten());
+                    usage.tested.extend(branch.variable.iter().flatten());
+                    // styles that test for a date print e.g. "n.d." without it, while other
+                    // variables are usually tested to just leave them out
+                    let dates = branch.variable.iter().flatten();
+                    let dates = dates.filter(|variable| matches!(variable, Variable::Date(_)));
+                    usage.printed.extend(dates);
+                    match self.applies(branch) {
+                        Some(true) => {
+                            self.elements(&branch.children, usage);
+                            return;
+                        }
+                        Some(false) => {}
+                        None => self.elements(&branch.children, usage),
+                    }
+                }
+                if let Some(otherwise) = &choose.otherwise {
+                    self.elements(&otherwise.children, usage);
+                }
+            }
+        }
+    }
+
+    /// Whether a branch applies to the entry, if that only depends on its types and on which
+    /// variables it has. Other conditions, such as the position of a citation, are assumed to
+    /// possibly apply.
+    fn applies(&self, branch: &ChooseBranch) -> Option<bool> {
+        let (kinds, present) = self.entry?;
+        let unknown = |tests: Option<usize>| std::iter::repeat_n(None, tests.unwrap_or(0));
+        let tests = branch
+            .type_
+            .iter()
+            .flatten()
+            .map(|kind| Some(kinds.contains(kind)))
+            .chain(
+                (branch.variable.iter().flatten())
+                    .map(|variable| Some(present.contains(&csl_name(variable)))),
+            )
+            .chain(unknown(branch.disambiguate.map(|_| 1)))
+            .chain(unknown(branch.is_numeric.as_ref().map(Vec::len)))
+            .chain(unknown(branch.is_uncertain_date.as_ref().map(Vec::len)))
+            .chain(unknown(branch.locator.as_ref().map(Vec::len)))
+            .chain(unknown(branch.position.as_ref().map(Vec::len)))
+            .collect::<Vec<_>>();
+
+        // the result is only known if the known tests decide it
+        let any = |result| tests.contains(&Some(result));
+        let all = |result| tests.iter().all(|test| *test == Some(result));
+        match branch.match_ {
+            ChooseMatch::All if any(false) => Some(false),
+            ChooseMatch::All if all(true) => Some(true),
+            ChooseMatch::Any if any(true) => Some(true),
+            ChooseMatch::Any if all(false) => Some(false),
+            ChooseMatch::None if any(true) => Some(false),
+            ChooseMatch::None if all(false) => Some(true),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missing_variables() {
+        let yaml = r#"
+            netwok:
+              type: article
+              title: Net Wok
+              author: Astley, Rick
+              date: 2020
+              parent:
+                type: periodical
+                title: Armenian Journal of Proceedings
+            dumplings:
+              type: article
+              title: Dumplings
+              parent:
+                type: periodical
+            wok-book:
+              type: book
+              title: The Wok Book
+              editor: Morris, Linda
+              date: 2021
+              publisher: Automattic
+        "#;
+        let library = hayagriva::io::from_yaml_str(yaml).unwrap();
+        let entries = library.iter().collect::<Vec<_>>();
+        let style = hayagriva::archive::ArchivedStyle::by_name("apa").unwrap();
+        let citationberg::Style::Independent(style) = style.get() else {
+            panic!("APA is an independent style");
+        };
+
+        let missing = missing_variables(
+            &style,
+            &entries,
+            LocaleCode::en_us(),
+            &hayagriva::archive::locales(),
+        );
+        assert_eq!(
+            missing,
+            [(
+                "dumplings".to_string(),
+                vec!["container-title".to_string(), "issued".to_string()]
+            )]
+        );
+    }
+}
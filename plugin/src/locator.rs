@@ -0,0 +1,275 @@
+//! Locators of citations, so that styles and locales can format them, e.g. "p. 5" as "S. 5" in
+//! German. They are parsed from the supplements of citations or given explicitly; supplements that
+//! are not recognized are rendered as given in the document.
+
+use std::str::FromStr;
+
+use hayagriva::citationberg::taxonomy::Locator;
+use hayagriva::{LocatorPayload, SpecificLocator, TransparentLocator};
+use typed_arena::Arena;
+
+use crate::error::{Error, ErrorKind};
+use crate::model::Supplement;
+use crate::suggest;
+
+/// The names of CSL's locator types.
+const NAMES: [&str; 29] = [
+    "act",
+    "appendix",
+    "article-locator",
+    "book",
+    "canon",
+    "chapter",
+    "column",
+    "elocation",
+    "equation",
+    "figure",
+    "folio",
+    "issue",
+    "line",
+    "note",
+    "opus",
+    "page",
+    "paragraph",
+    "part",
+    "rule",
+    "scene",
+    "section",
+    "sub-verbo",
+    "supplement",
+    "table",
+    "timestamp",
+    "title",
+    "title-locator",
+    "verse",
+    "volume",
+];
+
+/// The labels a supplement can start with, in singular and plural.
+const LABELS: [(&str, Locator); 44] = [
+    ("p.", Locator::Page),
+    ("pp.", Locator::Page),
+    ("page", Locator::Page),
+    ("pages", Locator::Page),
+    ("ch.", Locator::Chapter),
+    ("chap.", Locator::Chapter),
+    ("chaps.", Locator::Chapter),
+    ("chapter", Locator::Chapter),
+    ("chapters", Locator::Chapter),
+    ("§", Locator::Section),
+    ("§§", Locator::Section),
+    ("sec.", Locator::Section),
+    ("secs.", Locator::Section),
+    ("section", Locator::Section),
+    ("sections", Locator::Section),
+    ("¶", Locator::Paragraph),
+    ("para.", Locator::Paragraph),
+    ("paras.", Locator::Paragraph),
+    ("paragraph", Locator::Paragraph),
+    ("paragraphs", Locator::Paragraph),
+    ("vol.", Locator::Volume),
+    ("vols.", Locator::Volume),
+    ("volume", Locator::Volume),
+    ("volumes", Locator::Volume),
+    ("l.", Locator::Line),
+    ("ll.", Locator::Line),
+    ("line", Locator::Line),
+    ("lines", Locator::Line),
+    ("fig.", Locator::Figure),
+    ("figs.", Locator::Figure),
+    ("figure", Locator::Figure),
+    ("figures", Locator::Figure),
+    ("n.", Locator::Note),
+    ("nn.", Locator::Note),
+    ("note", Locator::Note),
+    ("notes", Locator::Note),
+    ("col.", Locator::Column),
+    ("cols.", Locator::Column),
+    ("pt.", Locator::Part),
+    ("part", Locator::Part),
+    ("v.", Locator::Verse),
+    ("vv.", Locator::Verse),
+    ("bk.", Locator::Book),
+    ("app.", Locator::Appendix),
+];
+
+/// The locator for a supplement. Values are allocated in the arena, since hayagriva borrows them.
+pub fn locator<'a>(
+    supplement: &Supplement,
+    values: &'a Arena<String>,
+) -> Result<SpecificLocator<'a>, Error> {
+    match supplement {
+        Supplement::Locator { locator, value } => {
+            let Ok(locator) = Locator::from_str(locator) else {
+                let message = format!("unknown locator `{locator}`");
+                let suggestions = suggest::suggest_names(locator, NAMES);
+                return Err(
+                    Error::new(ErrorKind::UnknownLocator, message).with_suggestions(suggestions)
+                );
+            };
+            let value = values.alloc(value.clone());
+            Ok(SpecificLocator(locator, LocatorPayload::Str(value)))
+        }
+        Supplement::Text(text) => match parse(text) {
+            Some((locator, value)) => {
+                let value = values.alloc(value.to_string());
+                Ok(SpecificLocator(locator, LocatorPayload::Str(value)))
+            }
+            None => Ok(SpecificLocator(
+                Locator::Custom,
+                LocatorPayload::Transparent(TransparentLocator::new(text.clone())),
+            )),
+        },
+    }
+}
+
+/// Parses a supplement such as "p. 5", "pp. 3-7", "chap. 2" or "§ 4" into its locator and value.
+fn parse(text: &str) -> Option<(Locator, &str)> {
+    let text = text.trim();
+    let (locator, value) = LABELS
+        .iter()
+        .filter_map(|&(label, locator)| {
+            let start = text.get(..label.len())?;
+            if !start.eq_ignore_ascii_case(label) {
+                return None;
+            }
+            let value = &text[label.len()..];
+            // words must be separated from the value, while e.g. "p.5" and "§4" are fine
+            let separated = value.starts_with(char::is_whitespace)
+                || !label.ends_with(|c: char| c.is_alphabetic());
+            separated.then_some((label.len(), locator, value.trim_start()))
+        })
+        .max_by_key(|(len, ..)| *len)
+        .map(|(_, locator, value)| (locator, value))?;
+
+    is_value(value).then_some((locator, value))
+}
+
+/// Whether text is a locator value: numbers, possibly roman or with a letter such as "12a",
+/// combined into ranges and lists, and optionally followed by "f." or "ff.".
+fn is_value(value: &str) -> bool {
+    let value = ["ff.", "f."]
+        .iter()
+        .find_map(|suffix| value.strip_suffix(suffix))
+        .map_or(value, str::trim_end);
+
+    let mut numbers = value
+        .split(['-', '–', '—', ',', '&'])
+        .map(str::trim)
+        .peekable();
+    numbers.peek().is_some()
+        && numbers.all(|number| {
+            let roman = is_roman(number);
+            let arabic = number.starts_with(|c: char| c.is_ascii_digit())
+                && number.chars().all(|c| c.is_ascii_alphanumeric());
+            !number.is_empty() && (roman || arabic)
+        })
+}
+
+/// Whether text is a well-formed roman numeral such as "xiv" or "XIV", so that words that only
+/// consist of roman digits, such as "mild", are not mistaken for one.
+fn is_roman(number: &str) -> bool {
+    const DIGITS: [(&str, u32); 13] = [
+        ("m", 1000),
+        ("cm", 900),
+        ("d", 500),
+        ("cd", 400),
+        ("c", 100),
+        ("xc", 90),
+        ("l", 50),
+        ("xl", 40),
+        ("x", 10),
+        ("ix", 9),
+        ("v", 5),
+        ("iv", 4),
+        ("i", 1),
+    ];
+
+    let lower = number.to_ascii_lowercase();
+    if number != lower && number != number.to_ascii_uppercase() {
+        return false;
+    }
+
+    let mut rest = lower.as_str();
+    let mut value = 0;
+    for (digit, digit_value) in DIGITS {
+        while let Some(next) = rest.strip_prefix(digit) {
+            rest = next;
+            value += digit_value;
+        }
+    }
+    if !rest.is_empty() || value == 0 {
+        return false;
+    }
+
+    // numerals such as "iiii" or "vv" add up, but aren't written that way
+    let mut canonical = String::new();
+    for (digit, digit_value) in DIGITS {
+        while value >= digit_value {
+            canonical.push_str(digit);
+            value -= digit_value;
+        }
+    }
+    canonical == lower
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("p. 5"), Some((Locator::Page, "5")));
+        assert_eq!(parse("pp. 3-7"), Some((Locator::Page, "3-7")));
+        assert_eq!(parse("pp. 3–7, 12"), Some((Locator::Page, "3–7, 12")));
+        assert_eq!(parse("p.12 ff."), Some((Locator::Page, "12 ff.")));
+        assert_eq!(parse("chap. 2"), Some((Locator::Chapter, "2")));
+        assert_eq!(parse("Chapter IV"), Some((Locator::Chapter, "IV")));
+        assert_eq!(parse("§ 4"), Some((Locator::Section, "4")));
+        assert_eq!(parse("§§ 4-6"), Some((Locator::Section, "4-6")));
+        assert_eq!(parse("vol. 3"), Some((Locator::Volume, "3")));
+        assert_eq!(parse("pp. xiv-xix"), Some((Locator::Page, "xiv-xix")));
+
+        assert_eq!(parse("5"), None);
+        assert_eq!(parse("p. 5, emphasis added"), None);
+        assert_eq!(parse("pages"), None);
+        assert_eq!(parse("notes5"), None);
+        assert_eq!(parse("[p. 1]"), None);
+        assert_eq!(parse("vol. mild"), None);
+        assert_eq!(parse("p. civil"), None);
+        assert_eq!(parse("chap. dim"), None);
+        assert_eq!(parse("p. iiii"), None);
+        assert_eq!(parse("chap. Iv"), None);
+    }
+
+    #[test]
+    fn test_locator() {
+        let values = Arena::new();
+        let explicit = Supplement::Locator {
+            locator: "chapter".to_string(),
+            value: "3".to_string(),
+        };
+        let SpecificLocator(kind, LocatorPayload::Str(value)) =
+            locator(&explicit, &values).unwrap()
+        else {
+            panic!("explicit locators have a value");
+        };
+        assert_eq!((kind, value), (Locator::Chapter, "3"));
+
+        let unknown = Supplement::Locator {
+            locator: "chaptr".to_string(),
+            value: "3".to_string(),
+        };
+        let error = locator(&unknown, &values).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::UnknownLocator);
+        assert_eq!(error.suggestions, ["chapter"]);
+
+        let text = Supplement::Text("as cited in the preface".to_string());
+        let SpecificLocator(kind, LocatorPayload::Transparent(_)) =
+            locator(&text, &values).unwrap()
+        else {
+            panic!("other supplements are rendered as given");
+        };
+        assert_eq!(kind, Locator::Custom);
+    }
+}
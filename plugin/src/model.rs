@@ -1,23 +1,113 @@
+use std::collections::HashMap;
+
 use hayagriva::{CitePurpose, ElemChild, ElemChildren};
 use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::error::{Diagnostic, Error};
+
 mod wrapper;
 
+/// The configuration of `read`, which `lib.typ` passes as a dictionary. Unknown fields are rejected
+/// so that misspelled options are not silently ignored.
 #[derive(Deserialize, Debug, Clone, PartialEq)]
-#[serde(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct Config {
-    pub sources: Vec<Resource>,
+    pub sources: Vec<Source>,
     pub full: bool,
     pub style: Style,
     pub locale: hayagriva::citationberg::LocaleCode,
+    #[serde(default)]
+    pub doi_keys: HashMap<String, String>,
+    #[serde(default)]
+    pub duplicates: Duplicates,
+    #[serde(default)]
+    pub deduplicate: bool,
+    #[serde(default)]
+    pub case_insensitive_keys: bool,
+    #[serde(default)]
+    pub missing: Missing,
+    /// Whether to skip invalid BibLaTeX entries instead of failing.
+    #[serde(default)]
+    pub skip_invalid: bool,
+    /// Whether to report variables the style uses but the cited entries don't have.
+    #[serde(default)]
+    pub lint: bool,
+    /// The keys of entries to include in the bibliography without citing them.
+    #[serde(default)]
+    pub nocite: Vec<String>,
     pub citations: Vec<Vec<Citation>>,
 }
 
+/// How to handle keys that appear in more than one source.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Duplicates {
+    #[default]
+    Error,
+    FirstWins,
+    LastWins,
+    /// Prefix the duplicated keys with a name derived from their sources' paths.
+    Namespace,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Source {
+    Resource(Resource),
+    Entries(InlineEntries),
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct InlineEntries {
+    pub entries: serde_yaml::Mapping,
+}
+
 #[derive(Deserialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct Resource {
     pub path: Option<String>,
-    pub data: String,
+    #[serde(deserialize_with = "deser_data")]
+    pub data: Vec<u8>,
+    #[serde(default)]
+    pub format: Option<Format>,
+    #[serde(default)]
+    pub encoding: Option<Encoding>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    #[serde(alias = "yaml")]
+    Hayagriva,
+    #[serde(alias = "bibtex")]
+    Biblatex,
+    CslJson,
+    Crossref,
+    Datacite,
+    Ris,
+    Endnote,
+    Mods,
+    Jats,
+    #[serde(alias = "pubmed")]
+    Medline,
+    Cff,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Encoding {
+    #[serde(rename = "utf-8", alias = "utf8")]
+    Utf8,
+    #[serde(rename = "utf-16", alias = "utf16")]
+    Utf16,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin-1", alias = "latin1", alias = "iso-8859-1")]
+    Latin1,
+    #[serde(rename = "windows-1252", alias = "cp1252")]
+    Windows1252,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -32,10 +122,58 @@ pub enum Style {
 pub struct Citation {
     pub key: String,
     #[serde(deserialize_with = "deser_cite_purpose")]
-    pub form: Option<Option<CitePurpose>>,
+    pub form: Option<CiteForm>,
     pub style: Option<String>,
-    pub supplement: Option<String>,
+    pub supplement: Option<Supplement>,
+    /// Text to put before the citation, within e.g. its parentheses.
+    #[serde(default)]
+    pub prefix: Option<String>,
+    /// Text to put after the citation, within e.g. its parentheses.
+    #[serde(default)]
+    pub suffix: Option<String>,
     pub locale: hayagriva::citationberg::LocaleCode,
+    /// The number of the footnote the citation's group becomes in a note style. Note styles use it
+    /// to tell whether an entry was cited in a near note.
+    #[serde(default)]
+    pub note: Option<usize>,
+}
+
+/// The form of a citation that is not hidden.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CiteForm {
+    Normal,
+    /// The normal form without the author, e.g. for "Smith argues (2020, p. 4)".
+    SuppressAuthor,
+    Purpose(CitePurpose),
+}
+
+impl CiteForm {
+    /// The purpose for which hayagriva renders the citation. Authors are suppressed afterwards.
+    pub fn purpose(self) -> Option<CitePurpose> {
+        match self {
+            Self::Normal | Self::SuppressAuthor => None,
+            Self::Purpose(purpose) => Some(purpose),
+        }
+    }
+}
+
+/// The supplement of a citation, which becomes its locator.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Supplement {
+    /// An explicit locator, e.g. `(locator: "page", value: "5")`.
+    Locator { locator: String, value: String },
+    /// The text of the supplement, or the `repr` of content that is not plain text. Unless a
+    /// locator such as "p. 5" can be parsed from it, the supplement is rendered as given.
+    Text(String),
+}
+
+/// The result of a plugin function, e.g. a bibliography, or the error that prevented it.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Response<T> {
+    Ok(T),
+    Error(Error),
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
@@ -44,12 +182,47 @@ pub struct Bibliography {
     pub references: Vec<RenderedReference>,
     pub citations: Vec<RenderedCitation>,
     pub hanging_indent: bool,
+    /// Problems that did not prevent reading the bibliography, including the invalid entries that
+    /// were skipped.
+    pub diagnostics: Vec<Diagnostic>,
+    pub merges: Vec<Merge>,
+    pub missing: Vec<String>,
+}
+
+/// How to handle citations of keys that are not in the bibliography.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Missing {
+    #[default]
+    Error,
+    /// Render a marker such as `[key?]` in place of the citation.
+    Placeholder,
+}
+
+/// An entry that was merged into another one describing the same work.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct Merge {
+    pub key: String,
+    pub into: String,
+    pub reason: MergeReason,
+}
+
+/// The identifier that two entries were found to share.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum MergeReason {
+    Doi,
+    Isbn,
+    Arxiv,
+    TitleYear,
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct RenderedReference {
     pub key: String,
+    pub aliases: Vec<String>,
     #[serde(serialize_with = "wrapper::ser_wrapped_option")]
     pub first_field: Option<ElemChild>,
     #[serde(serialize_with = "wrapper::ser_wrapped")]
@@ -65,7 +238,56 @@ pub struct RenderedCitation {
     pub content: ElemChildren,
 }
 
-fn deser_cite_purpose<'de, D>(deserializer: D) -> Result<Option<Option<CitePurpose>>, D::Error>
+fn deser_data<'de, D>(deserializer: D) -> Result<Vec<u8>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use std::fmt;
+
+    use serde::de::{self, Visitor};
+
+    struct DataVisitor;
+
+    impl<'de> Visitor<'de> for DataVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("bibliography data as bytes or a string")
+        }
+
+        fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.to_vec())
+        }
+
+        fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value)
+        }
+
+        fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.as_bytes().to_vec())
+        }
+
+        fn visit_string<E>(self, value: String) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Ok(value.into_bytes())
+        }
+    }
+
+    deserializer.deserialize_any(DataVisitor)
+}
+
+fn deser_cite_purpose<'de, D>(deserializer: D) -> Result<Option<CiteForm>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -76,10 +298,12 @@ where
     struct CitePurposeVisitor;
 
     impl<'de> Visitor<'de> for CitePurposeVisitor {
-        type Value = Option<Option<CitePurpose>>;
+        type Value = Option<CiteForm>;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-            formatter.write_str("a valid citation form: normal, prose, full, author, or year")
+            formatter.write_str(
+                "a valid citation form: normal, prose, full, author, year, or suppress-author",
+            )
         }
 
         fn visit_none<E>(self) -> Result<Self::Value, E>
@@ -94,11 +318,12 @@ where
             E: de::Error,
         {
             match value {
-                "normal" => Ok(Some(None)),
-                "prose" => Ok(Some(Some(CitePurpose::Prose))),
-                "full" => Ok(Some(Some(CitePurpose::Full))),
-                "author" => Ok(Some(Some(CitePurpose::Author))),
-                "year" => Ok(Some(Some(CitePurpose::Year))),
+                "normal" => Ok(Some(CiteForm::Normal)),
+                "prose" => Ok(Some(CiteForm::Purpose(CitePurpose::Prose))),
+                "full" => Ok(Some(CiteForm::Purpose(CitePurpose::Full))),
+                "author" => Ok(Some(CiteForm::Purpose(CitePurpose::Author))),
+                "year" => Ok(Some(CiteForm::Purpose(CitePurpose::Year))),
+                "suppress-author" => Ok(Some(CiteForm::SuppressAuthor)),
                 _ => Err(de::Error::invalid_value(de::Unexpected::Str(value), &self)),
             }
         }
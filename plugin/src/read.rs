@@ -1,74 +1,348 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt::Display;
 use std::path::Path;
 
-use hayagriva::{io::BibLaTeXError, Library};
+use hayagriva::{Entry, Library};
 use typst_syntax::Lines;
 
-use crate::model::Resource;
+use crate::error::{Diagnostic, Error, ErrorKind};
+use crate::model::{Format, InlineEntries, Resource, Source};
+
+use self::biblatex::BiblatexSource;
+
+mod biblatex;
+mod builder;
+mod cff;
+mod csl;
+mod detect;
+mod doi;
+mod encoding;
+mod endnote;
+mod jats;
+mod mods;
+mod nbib;
+mod ris;
+mod xml;
 
 // heavily based on https://github.com/typst/typst/blob/main/crates/typst-library/src/model/bibliography.rs#L306-L390
 
-/// Decode on library from one data source.
-pub fn decode_library(source: &Resource) -> Result<Library, String> {
-    let Resource { path, data } = source;
-
-    if let Some(path) = path {
-        // If we got a path, use the extension to determine whether it is
-        // YAML or BibLaTeX.
-        let ext = Path::new(&path)
-            .extension()
-            .and_then(OsStr::to_str)
-            .unwrap_or_default();
-
-        match ext.to_lowercase().as_str() {
-            "yml" | "yaml" => {
-                hayagriva::io::from_yaml_str(data).map_err(|err| format_yaml_error(Some(path), err))
+/// The entries decoded from one data source.
+#[derive(Debug)]
+pub struct DecodedLibrary {
+    pub library: Library,
+    /// Additional keys the entries can be cited by, given by their `ids` fields, together with the
+    /// keys of the entries.
+    pub aliases: Vec<(String, String)>,
+    /// The invalid entries that were left out, if they are to be skipped.
+    pub skipped: Vec<Error>,
+    /// Problems with the entries that were read.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The lines and columns at which the entries are defined, if known.
+    pub locations: HashMap<String, (usize, usize)>,
+}
+
+impl From<Library> for DecodedLibrary {
+    fn from(library: Library) -> Self {
+        Self {
+            library,
+            aliases: Vec::new(),
+            skipped: Vec::new(),
+            diagnostics: Vec::new(),
+            locations: HashMap::new(),
+        }
+    }
+}
+
+/// Decode one library from each data source. BibLaTeX sources are decoded together, since they may
+/// share `@string` macros and entries. Entries of formats that identify them by DOI are keyed
+/// according to `doi_keys`. Invalid BibLaTeX entries are skipped if `skip_invalid` is set.
+pub fn decode_libraries(
+    sources: &[Source],
+    doi_keys: &HashMap<String, String>,
+    skip_invalid: bool,
+) -> Result<Vec<DecodedLibrary>, Error> {
+    let texts = sources
+        .iter()
+        .map(|source| match source {
+            Source::Resource(resource) => decode_text(resource).map(Some),
+            Source::Entries(_) => Ok(None),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let biblatex_sources = sources
+        .iter()
+        .zip(&texts)
+        .filter_map(|(source, text)| match (source, text) {
+            (Source::Resource(resource), Some((text, Format::Biblatex))) => Some(BiblatexSource {
+                path: resource.path.as_deref(),
+                text,
+            }),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let mut biblatex_libraries =
+        biblatex::from_biblatex_sources(&biblatex_sources, skip_invalid)?.into_iter();
+
+    sources
+        .iter()
+        .zip(&texts)
+        .map(|(source, text)| match (source, text) {
+            (Source::Resource(_), Some((_, Format::Biblatex))) => Ok(biblatex_libraries
+                .next()
+                .expect("one library per BibLaTeX source")),
+            (Source::Resource(resource), Some((text, Format::Hayagriva))) => {
+                let library =
+                    decode_library(resource.path.as_deref(), text, Format::Hayagriva, doi_keys)?;
+                // if the YAML is valid, Hayagriva could parse it
+                let aliases = serde_yaml::from_str(text)
+                    .map(|entries| yaml_aliases(&entries))
+                    .unwrap_or_default();
+                Ok(DecodedLibrary {
+                    library,
+                    aliases,
+                    skipped: Vec::new(),
+                    diagnostics: Vec::new(),
+                    locations: yaml_locations(text),
+                })
+            }
+            (Source::Resource(resource), Some((text, format))) => {
+                decode_library(resource.path.as_deref(), text, *format, doi_keys).map(Into::into)
+            }
+            (Source::Entries(entries), _) => {
+                let library = decode_entries(entries)?;
+                let aliases = yaml_aliases(&entries.entries);
+                Ok(DecodedLibrary {
+                    library,
+                    aliases,
+                    skipped: Vec::new(),
+                    diagnostics: Vec::new(),
+                    locations: HashMap::new(),
+                })
             }
-            "bib" => hayagriva::io::from_biblatex_str(data)
-                .map_err(|err| format_biblatex_error(Some(path), data, err)),
-            _ => Err("unknown bibliography format (must be .yaml/.yml or .bib)".to_string()),
+            (Source::Resource(_), None) => unreachable!("resources are always decoded"),
+        })
+        .collect()
+}
+
+/// Decode a data source into text and determine its format.
+fn decode_text(source: &Resource) -> Result<(Cow<'_, str>, Format), Error> {
+    let Resource {
+        path,
+        data,
+        format,
+        encoding,
+    } = source;
+    let path = path.as_deref();
+
+    let data = encoding::decode(data, *encoding).map_err(|err| {
+        format_error(
+            ErrorKind::Decode,
+            "failed to decode bibliography",
+            err,
+            path,
+            None,
+        )
+    })?;
+
+    // An explicitly given format always wins. Otherwise, use the extension if
+    // it determines the format, and look at the data if it doesn't.
+    let format = format
+        .or_else(|| {
+            let ext = Path::new(path?).extension().and_then(OsStr::to_str)?;
+            detect::from_extension(ext)
+        })
+        .or_else(|| detect::detect(&data))
+        .ok_or_else(|| {
+            format_error(
+                ErrorKind::UnknownFormat,
+                "unknown bibliography format",
+                "specify one using the `format` parameter",
+                path,
+                None,
+            )
+        })?;
+
+    Ok((data, format))
+}
+
+/// Decode one library from the text of a data source in the given format.
+fn decode_library(
+    path: Option<&str>,
+    data: &str,
+    format: Format,
+    doi_keys: &HashMap<String, String>,
+) -> Result<Library, Error> {
+    match format {
+        Format::Hayagriva => {
+            hayagriva::io::from_yaml_str(data).map_err(|err| format_yaml_error(path, err))
         }
-    } else {
-        // If we just got bytes, we need to guess. If it can be decoded as
-        // hayagriva YAML, we'll use that.
-        let haya_err = match hayagriva::io::from_yaml_str(data) {
-            Ok(library) => return Ok(library),
-            Err(err) => err,
-        };
+        Format::Biblatex => {
+            let source = BiblatexSource { path, text: data };
+            biblatex::from_biblatex_sources(&[source], false)
+                .map(|mut libraries| libraries.remove(0).library)
+        }
+        Format::CslJson => parse_json(data)
+            .and_then(csl::from_csl_json)
+            .map_err(|err| format_decode_error("CSL-JSON", path, err)),
+        Format::Crossref | Format::Datacite => parse_json(data)
+            .and_then(|value| doi::from_doi_json(&value, doi_keys))
+            .map_err(|err| format_decode_error("DOI metadata", path, err)),
+        Format::Ris => ris::from_ris_str(data).map_err(|err| format_decode_error("RIS", path, err)),
+        Format::Endnote => xml::parse(data)
+            .and_then(|root| endnote::from_endnote_xml(&root))
+            .map_err(|err| format_decode_error("EndNote XML", path, err)),
+        Format::Mods => xml::parse(data)
+            .and_then(|root| mods::from_mods(&root))
+            .map_err(|err| format_decode_error("MODS", path, err)),
+        Format::Jats => xml::parse(data)
+            .and_then(|root| jats::from_jats(&root))
+            .map_err(|err| format_decode_error("JATS", path, err)),
+        Format::Medline => {
+            nbib::from_nbib_str(data).map_err(|err| format_decode_error("MEDLINE", path, err))
+        }
+        Format::Cff => {
+            cff::from_cff_str(data).map_err(|err| format_decode_error("CITATION.cff", path, err))
+        }
+    }
+}
 
-        // If it can be decoded as BibLaTeX, we use that instead.
-        let bib_errs = match hayagriva::io::from_biblatex_str(data) {
-            // If the file is almost valid yaml, but contains no `@` character
-            // it will be successfully parsed as an empty BibLaTeX library,
-            // since BibLaTeX does support arbitrary text outside of entries.
-            Ok(library) if !library.is_empty() => return Ok(library),
-            Ok(_) => None,
-            Err(err) => Some(err),
+/// Decode the entries given directly as a map of keys to fields, which follow the same rules as
+/// Hayagriva YAML.
+fn decode_entries(source: &InlineEntries) -> Result<Library, Error> {
+    let entries = serde_yaml::Value::Mapping(source.entries.clone());
+    serde_yaml::from_value(entries).map_err(|err| {
+        format_error(
+            ErrorKind::Parse,
+            "failed to parse inline entries",
+            err,
+            None,
+            None,
+        )
+    })
+}
+
+/// Reads the aliases of entries given in Hayagriva's YAML format. Like in BibLaTeX, they are given
+/// in an `ids` field, either as a list or separated by commas. Hayagriva itself ignores the field.
+fn yaml_aliases(entries: &serde_yaml::Mapping) -> Vec<(String, String)> {
+    let mut aliases = Vec::new();
+    for (key, fields) in entries {
+        let (Some(key), Some(ids)) = (key.as_str(), fields.get("ids")) else {
+            continue;
+        };
+        let ids = match ids {
+            serde_yaml::Value::Sequence(ids) => ids.iter().filter_map(|id| id.as_str()).collect(),
+            serde_yaml::Value::String(ids) => ids.split(',').collect(),
+            _ => Vec::new(),
         };
+        aliases.extend(
+            ids.into_iter()
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(|id| (id.to_string(), key.to_string())),
+        );
+    }
+    aliases
+}
 
-        // If neither decoded correctly, check whether `:` or `{` appears
-        // more often to guess whether it's more likely to be YAML or BibLaTeX
-        // and emit the more appropriate error.
-        let mut yaml = 0;
-        let mut biblatex = 0;
-        for c in data.chars() {
-            match c {
-                ':' => yaml += 1,
-                '{' => biblatex += 1,
-                _ => {}
-            }
+/// Finds the lines of the top-level keys of a Hayagriva YAML file.
+fn yaml_locations(text: &str) -> HashMap<String, (usize, usize)> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.starts_with([' ', '\t', '#', '-']))
+        .filter_map(|(i, line)| {
+            let (key, _) = line.split_once(':')?;
+            let key = key.trim().trim_matches(['"', '\'']);
+            Some((key.to_string(), (i + 1, 1)))
+        })
+        .collect()
+}
+
+/// Returns a copy of the entry under a different key.
+pub fn rekey(entry: &Entry, key: &str) -> Result<Entry, Error> {
+    let error = |err: serde_yaml::Error| {
+        format_error(ErrorKind::Parse, "failed to rename entry", err, None, None).with_key(key)
+    };
+    let fields = serde_yaml::to_value(entry).map_err(error)?;
+    let mut entries = serde_yaml::Mapping::new();
+    entries.insert(key.into(), fields);
+    let library: Library =
+        serde_yaml::from_value(serde_yaml::Value::Mapping(entries)).map_err(error)?;
+    Ok(library
+        .into_iter()
+        .next()
+        .expect("library contains the inserted entry"))
+}
+
+fn parse_json(data: &str) -> Result<serde_json::Value, DecodeError> {
+    serde_json::from_str(data).map_err(DecodeError::from_json)
+}
+
+/// An error while decoding one of the formats that are converted into Hayagriva
+/// entries by this crate.
+#[derive(Debug)]
+pub struct DecodeError {
+    message: String,
+    /// One-based line and column of the error, if known.
+    location: Option<(usize, usize)>,
+}
+
+impl DecodeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            location: None,
         }
+    }
+
+    pub fn at(message: impl Into<String>, location: (usize, usize)) -> Self {
+        Self {
+            message: message.into(),
+            location: Some(location),
+        }
+    }
+
+    pub fn from_json(error: serde_json::Error) -> Self {
+        // the location is reported separately, don't repeat it in the message
+        let message = error.to_string();
+        let message = match message.rsplit_once(" at line ") {
+            Some((message, _)) => message.to_string(),
+            None => message,
+        };
+        Self::at(message, (error.line(), error.column()))
+    }
+}
 
-        match bib_errs {
-            Some(bib_errs) if biblatex >= yaml => Err(format_biblatex_error(None, data, bib_errs)),
-            _ => Err(format_yaml_error(None, haya_err)),
+impl From<serde_yaml::Error> for DecodeError {
+    fn from(error: serde_yaml::Error) -> Self {
+        let location = error.location().map(|loc| (loc.line(), loc.column()));
+        let mut message = error.to_string();
+        if let Some(location) = error.location() {
+            // the location is reported separately, don't repeat it in the message
+            let suffix = format!(" at line {} column {}", location.line(), location.column());
+            if let Some(stripped) = message.strip_suffix(&suffix) {
+                message = stripped.to_string();
+            }
         }
+        Self { message, location }
     }
 }
 
-pub fn format_yaml_error(path: Option<&str>, error: serde_yaml::Error) -> String {
+/// Format an error of one of the formats that are converted by this crate.
+fn format_decode_error(format: &str, path: Option<&str>, error: DecodeError) -> Error {
     format_error(
+        ErrorKind::Parse,
+        &format!("failed to parse {format}"),
+        error.message,
+        path,
+        error.location,
+    )
+}
+
+pub fn format_yaml_error(path: Option<&str>, error: serde_yaml::Error) -> Error {
+    format_error(
+        ErrorKind::Parse,
         "failed to parse YAML",
         &error,
         path,
@@ -76,44 +350,26 @@ pub fn format_yaml_error(path: Option<&str>, error: serde_yaml::Error) -> String
     )
 }
 
-/// Format a BibLaTeX loading error.
-fn format_biblatex_error(path: Option<&str>, text: &str, errors: Vec<BibLaTeXError>) -> String {
-    // TODO: return multiple errors?
-    let Some(error) = errors.into_iter().next() else {
-        // TODO: can this even happen, should we just unwrap?
-        return format_error(
-            "failed to parse BibLaTeX",
-            "something went wrong",
-            path,
-            None,
-        );
-    };
-
-    let (range, msg) = match error {
-        BibLaTeXError::Parse(error) => (error.span, error.kind.to_string()),
-        BibLaTeXError::Type(error) => (error.span, error.kind.to_string()),
-    };
-
-    // TODO process range
-    let loc = {
-        let lines = Lines::new(text);
-        lines
-            .byte_to_line_column(range.start)
-            .map(|(line, column)| (line + 1, column + 1))
-    };
-    format_error("failed to parse BibLaTeX", msg, path, loc)
+/// Converts a byte offset into a one-based line and column.
+fn line_column(text: &str, offset: usize) -> Option<(usize, usize)> {
+    let lines = Lines::new(text);
+    lines
+        .byte_to_line_column(offset)
+        .map(|(line, column)| (line + 1, column + 1))
 }
 
 fn format_error(
+    kind: ErrorKind,
     msg: &str,
     detail: impl Display,
     path: Option<&str>,
     location: Option<(usize, usize)>,
-) -> String {
-    match (path, location) {
+) -> Error {
+    let message = match (path, location) {
         (Some(path), Some((line, column))) => format!("{msg} ({path}:{line}:{column}: {detail})"),
         (Some(path), None) => format!("{msg} ({path}: {detail})"),
         (None, Some((line, column))) => format!("{msg} (<input>:{line}:{column}: {detail})"),
         (None, None) => format!("{msg} ({detail})"),
-    }
+    };
+    Error::new(kind, message).with_path(path, location)
 }
@@ -0,0 +1,818 @@
+//! Decoding of BibLaTeX files. Like biber does, all files of one bibliography share their
+//! `@string` macros and entries, so that a `crossref` parent, an `@xdata` entry or a macro can be
+//! defined in a different file than the entries using it.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use ::biblatex::{
+    Bibliography, Chunk, Date, Pair, ParseError, ParseErrorKind, PermissiveType, RawBibliography,
+    RawChunk, RawEntry, Spanned, TypeError,
+};
+use hayagriva::Library;
+
+use crate::error::{Diagnostic, Error, ErrorKind};
+
+use super::{format_error, line_column, DecodedLibrary};
+
+/// The fields of the BibLaTeX data model and their BibTeX aliases, as well as some fields commonly
+/// written by reference managers. Other fields are reported, since they are likely misspelled.
+const KNOWN_FIELDS: &[&str] = &[
+    "abstract",
+    "addendum",
+    "address",
+    "afterword",
+    "annotation",
+    "annotator",
+    "annote",
+    "archiveprefix",
+    "author",
+    "authortype",
+    "bookauthor",
+    "bookpagination",
+    "booksubtitle",
+    "booktitle",
+    "booktitleaddon",
+    "chapter",
+    "commentator",
+    "crossref",
+    "date",
+    "doi",
+    "edition",
+    "editor",
+    "editora",
+    "editoratype",
+    "editorb",
+    "editorbtype",
+    "editorc",
+    "editorctype",
+    "editortype",
+    "eid",
+    "entryset",
+    "entrysubtype",
+    "eprint",
+    "eprintclass",
+    "eprinttype",
+    "eventdate",
+    "eventtitle",
+    "eventtitleaddon",
+    "execute",
+    "file",
+    "foreword",
+    "gender",
+    "holder",
+    "howpublished",
+    "ids",
+    "indexsorttitle",
+    "indextitle",
+    "institution",
+    "introduction",
+    "isan",
+    "isbn",
+    "ismn",
+    "isrn",
+    "issn",
+    "issue",
+    "issuesubtitle",
+    "issuetitle",
+    "issuetitleaddon",
+    "iswc",
+    "journal",
+    "journalsubtitle",
+    "journaltitle",
+    "journaltitleaddon",
+    "key",
+    "keywords",
+    "label",
+    "langid",
+    "langidopts",
+    "language",
+    "library",
+    "lista",
+    "listb",
+    "listc",
+    "listd",
+    "liste",
+    "listf",
+    "location",
+    "mainsubtitle",
+    "maintitle",
+    "maintitleaddon",
+    "month",
+    "mrnumber",
+    "namea",
+    "nameaddon",
+    "nameatype",
+    "nameb",
+    "namebtype",
+    "namec",
+    "namectype",
+    "note",
+    "number",
+    "options",
+    "organization",
+    "origdate",
+    "origlanguage",
+    "origlocation",
+    "origpublisher",
+    "origtitle",
+    "owner",
+    "pages",
+    "pagetotal",
+    "pagination",
+    "part",
+    "pdf",
+    "pmcid",
+    "pmid",
+    "presort",
+    "primaryclass",
+    "publisher",
+    "pubstate",
+    "related",
+    "relatedoptions",
+    "relatedstring",
+    "relatedtype",
+    "reprinttitle",
+    "school",
+    "series",
+    "shortauthor",
+    "shorteditor",
+    "shorthand",
+    "shorthandintro",
+    "shortjournal",
+    "shortseries",
+    "shorttitle",
+    "sortkey",
+    "sortname",
+    "sortshorthand",
+    "sorttitle",
+    "sortyear",
+    "subtitle",
+    "timestamp",
+    "title",
+    "titleaddon",
+    "translator",
+    "type",
+    "url",
+    "urldate",
+    "usera",
+    "userb",
+    "userc",
+    "userd",
+    "usere",
+    "userf",
+    "venue",
+    "verba",
+    "verbb",
+    "verbc",
+    "version",
+    "volume",
+    "volumes",
+    "xdata",
+    "xref",
+    "year",
+];
+
+/// The date fields, whose values are ignored if they can't be parsed.
+const DATE_FIELDS: &[&str] = &["date", "eventdate", "origdate", "urldate"];
+
+/// The fields whose values are not text, and may contain backslashes.
+const VERBATIM_FIELDS: &[&str] = &[
+    "doi", "eprint", "file", "pdf", "url", "verba", "verbb", "verbc",
+];
+
+/// A BibLaTeX file, after it has been decoded into text.
+pub struct BiblatexSource<'a> {
+    pub path: Option<&'a str>,
+    pub text: &'a str,
+}
+
+/// Decodes all BibLaTeX files of a bibliography together, returning one library per file. Each
+/// library only contains the entries of its own file, with the data inherited from parents in
+/// other files already filled in.
+///
+/// All invalid entries are reported, not just the first one. If `skip_invalid` is set, they are
+/// left out of the libraries and returned as their `skipped` entries instead of failing.
+pub fn from_biblatex_sources(
+    sources: &[BiblatexSource],
+    skip_invalid: bool,
+) -> Result<Vec<DecodedLibrary>, Error> {
+    let mut problems = vec![Vec::new(); sources.len()];
+    let texts = sources
+        .iter()
+        .zip(&mut problems)
+        .map(|(source, problems)| remove_unparsable(source, problems))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut raws = texts
+        .iter()
+        .map(|text| RawBibliography::parse(text).expect("unparsable entries were removed"))
+        .collect::<Vec<_>>();
+    let mut invalid = problems
+        .iter()
+        .flatten()
+        .filter_map(|problem| problem.key.clone())
+        .collect::<HashSet<_>>();
+    remove_orphans(sources, &mut raws, &mut invalid, &mut problems);
+
+    // resolve every file on its own first, so that errors are reported for the right file
+    let mut bibliographies = Vec::with_capacity(sources.len());
+    for (i, source) in sources.iter().enumerate() {
+        let bibliography = loop {
+            let own = raws[i].entries.clone();
+            let error = match Bibliography::from_raw(with_macros(own, &raws, i)) {
+                Ok(bibliography) => break bibliography,
+                Err(error) => error,
+            };
+            let entries = &mut raws[i].entries;
+            let position = entries
+                .iter()
+                .position(|entry| entry.span.contains(&error.span.start));
+            let problem = parse_error(source, error);
+            let Some(position) = position else {
+                return Err(problem);
+            };
+            let key = entries.remove(position).v.key.v;
+            invalid.insert(key.to_string());
+            problems[i].push(problem.with_key(key));
+        };
+        bibliographies.push(bibliography);
+    }
+    // entries inheriting from the entries that were just removed can't be resolved either
+    for (i, key) in remove_orphans(sources, &mut raws, &mut invalid, &mut problems) {
+        bibliographies[i].remove(&key);
+    }
+
+    // the first file defining a key is the one that is used; duplicates are reported later
+    let mut entries = HashMap::new();
+    for raw in &raws {
+        for entry in &raw.entries {
+            entries.entry(entry.v.key.v).or_insert(entry);
+        }
+    }
+
+    // then resolve the files that inherit from other files again, this time including the
+    // (transitive) parents they need
+    for (i, (source, raw)) in sources.iter().zip(&raws).enumerate() {
+        let own = raw
+            .entries
+            .iter()
+            .map(|entry| entry.v.key.v)
+            .collect::<HashSet<_>>();
+        let mut foreign = Vec::new();
+        let mut pending = raw.entries.iter().collect::<Vec<_>>();
+        let mut seen = own.clone();
+        while let Some(entry) = pending.pop() {
+            for (_, parent) in parents(&entry.v) {
+                let parent = entries[parent.v.as_str()];
+                if seen.insert(parent.v.key.v) {
+                    foreign.push(parent.v.key.v);
+                    pending.push(parent);
+                }
+            }
+        }
+        if foreign.is_empty() {
+            continue;
+        }
+
+        let mut all = raw.entries.clone();
+        all.extend(foreign.iter().map(|key| entries[key].clone()));
+        let mut bibliography = Bibliography::from_raw(with_macros(all, &raws, i))
+            .map_err(|err| parse_error(source, err))?;
+        for key in foreign {
+            bibliography.remove(key);
+        }
+        bibliographies[i] = bibliography;
+    }
+
+    let mut libraries = Vec::with_capacity(sources.len());
+    for (i, (source, bibliography)) in sources.iter().zip(bibliographies).enumerate() {
+        let mut library = Library::new();
+        for entry in bibliography.iter() {
+            match hayagriva::Entry::try_from(entry) {
+                Ok(entry) => library.push(&entry),
+                Err(error) => {
+                    // inherited fields point into the file of the parent
+                    let own = raws[i]
+                        .entries
+                        .iter()
+                        .find(|raw| raw.v.key.v == entry.key)
+                        .map(|raw| raw.span.clone())
+                        .unwrap_or_else(|| error.span.clone());
+                    let offset = if own.contains(&error.span.start) {
+                        error.span.start
+                    } else {
+                        own.start
+                    };
+                    problems[i].push(type_error(source, offset, error).with_key(&entry.key));
+                }
+            }
+        }
+        let aliases = aliases(&bibliography)
+            .into_iter()
+            .filter(|(_, key)| library.get(key).is_some())
+            .collect();
+        let diagnostics = raws[i]
+            .entries
+            .iter()
+            .filter(|raw| library.get(raw.v.key.v).is_some())
+            .flat_map(|raw| {
+                let entry = bibliography.get(raw.v.key.v).expect("entry was converted");
+                check_entry(source, &raw.v, entry)
+            })
+            .collect();
+        let locations = raws[i]
+            .entries
+            .iter()
+            .filter_map(|raw| {
+                let location = line_column(source.text, raw.span.start)?;
+                Some((raw.v.key.v.to_string(), location))
+            })
+            .collect();
+        let mut skipped = std::mem::take(&mut problems[i]);
+        skipped.sort_by_key(|problem| (problem.line, problem.column));
+        libraries.push(DecodedLibrary {
+            library,
+            aliases,
+            skipped,
+            diagnostics,
+            locations,
+        });
+    }
+
+    if !skip_invalid {
+        let errors = libraries
+            .iter_mut()
+            .flat_map(|library| std::mem::take(&mut library.skipped))
+            .collect();
+        if let Some(error) = Error::combine(errors) {
+            return Err(error);
+        }
+    }
+    Ok(libraries)
+}
+
+/// Parses a file, replacing every entry that can't be parsed with whitespace so that the rest of
+/// the file can still be parsed. The errors are added to `problems`.
+fn remove_unparsable<'a>(
+    source: &BiblatexSource<'a>,
+    problems: &mut Vec<Error>,
+) -> Result<Cow<'a, str>, Error> {
+    let mut text = Cow::Borrowed(source.text);
+    loop {
+        let Err(mut error) = RawBibliography::parse(&text) else {
+            return Ok(text);
+        };
+        let range = broken_entry(&text, error.span.start);
+        if !range.contains(&error.span.start) {
+            // the parser only noticed in one of the following entries
+            error.span = range.start..range.start;
+        }
+        let problem = parse_error(source, error);
+        let entry = &text[range.clone()];
+        if entry.trim().is_empty() {
+            // nothing left to remove, the error can't be recovered from
+            return Err(problem);
+        }
+        problems.push(match raw_key(entry) {
+            Some(key) => problem.with_key(key),
+            None => problem,
+        });
+
+        // keep the offsets and lines of the following entries
+        let blank = entry
+            .bytes()
+            .map(|b| if b == b'\n' { '\n' } else { ' ' })
+            .collect::<String>();
+        text.to_mut().replace_range(range, &blank);
+    }
+}
+
+/// The range of the entry a parse error at the given offset is attributed to. Entries are assumed
+/// to start with an `@` at the beginning of a line. Since the parser runs into the following
+/// entries when a brace is not closed, the first entry with unbalanced braces is preferred over
+/// the one the error occurs in.
+fn broken_entry(text: &str, offset: usize) -> Range<usize> {
+    let mut starts = vec![0];
+    starts.extend(
+        text.match_indices('\n')
+            .map(|(i, _)| i + 1)
+            .filter(|&i| text[i..].trim_start_matches([' ', '\t']).starts_with('@')),
+    );
+    let ends = starts.iter().skip(1).copied().chain([text.len()]);
+
+    let mut containing = 0..text.len();
+    for range in starts
+        .iter()
+        .copied()
+        .zip(ends)
+        .map(|(start, end)| start..end)
+    {
+        if range.start > offset {
+            break;
+        }
+        if !balanced(&text[range.clone()]) {
+            return range;
+        }
+        containing = range;
+    }
+    containing
+}
+
+fn balanced(text: &str) -> bool {
+    let mut depth = 0;
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            '{' if !escaped => depth += 1,
+            '}' if !escaped => depth -= 1,
+            _ => {}
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    depth == 0
+}
+
+/// The key of an entry that could not be parsed, if it can be made out.
+fn raw_key(entry: &str) -> Option<&str> {
+    let (_, rest) = entry.split_once(['{', '('])?;
+    let key = rest.split(',').next()?.trim();
+    let valid = !key.is_empty() && !key.contains(char::is_whitespace);
+    valid.then_some(key)
+}
+
+/// Removes the entries whose `crossref` or `xdata` parents are not defined or invalid, until all
+/// remaining entries have their parents. Returns the indices of the files and the keys of the
+/// removed entries.
+fn remove_orphans(
+    sources: &[BiblatexSource],
+    raws: &mut [RawBibliography],
+    invalid: &mut HashSet<String>,
+    problems: &mut [Vec<Error>],
+) -> Vec<(usize, String)> {
+    let mut removed = Vec::new();
+    loop {
+        let defined = raws
+            .iter()
+            .flat_map(|raw| &raw.entries)
+            .map(|entry| entry.v.key.v)
+            .collect::<HashSet<_>>();
+        let count = removed.len();
+        for (i, (source, raw)) in sources.iter().zip(raws.iter_mut()).enumerate() {
+            raw.entries.retain(|entry| {
+                let Some((field, parent)) = parents(&entry.v)
+                    .into_iter()
+                    .find(|(_, parent)| !defined.contains(parent.v.as_str()))
+                else {
+                    return true;
+                };
+                let key = entry.v.key.v;
+                let reason = if invalid.contains(&parent.v) {
+                    "is invalid"
+                } else {
+                    "is not defined in any bibliography"
+                };
+                let detail = format!(
+                    "`{field}` of entry `{key}` refers to `{}`, which {reason}",
+                    parent.v
+                );
+                let location = line_column(source.text, parent.span.start);
+                problems[i].push(
+                    format_error(
+                        ErrorKind::Parse,
+                        "failed to parse BibLaTeX",
+                        detail,
+                        source.path,
+                        location,
+                    )
+                    .with_key(key),
+                );
+                removed.push((i, key.to_string()));
+                false
+            });
+        }
+        if removed.len() == count {
+            break;
+        }
+        invalid.extend(removed[count..].iter().map(|(_, key)| key.clone()));
+    }
+    removed
+}
+
+/// Reports the problems with an entry that don't make it invalid: unknown fields, dates that can't
+/// be parsed, and LaTeX commands that are neither resolved nor rendered.
+fn check_entry(
+    source: &BiblatexSource,
+    raw: &RawEntry,
+    entry: &::biblatex::Entry,
+) -> Vec<Diagnostic> {
+    let key = raw.key.v;
+    let mut diagnostics = Vec::new();
+    let mut warn = |message: String, offset: usize| {
+        let location = line_column(source.text, offset);
+        diagnostics.push(
+            Diagnostic::warning(message)
+                .with_key(key)
+                .with_path(source.path, location),
+        );
+    };
+
+    for field in &raw.fields {
+        let name = field.key.v.to_ascii_lowercase();
+        let name = name.as_str();
+        if !KNOWN_FIELDS.contains(&name) {
+            warn(
+                format!("unknown field `{name}` in entry `{key}` was ignored"),
+                field.key.span.start,
+            );
+            continue;
+        }
+
+        if DATE_FIELDS.contains(&name) {
+            if let Ok(PermissiveType::Chunks(chunks)) = entry.get_as::<PermissiveType<Date>>(name) {
+                let value = chunks.iter().map(|chunk| chunk.v.get()).collect::<String>();
+                warn(
+                    format!(
+                        "`{name}` of entry `{key}` is not a valid date and was ignored: {value}"
+                    ),
+                    field.value.span.start,
+                );
+            }
+        } else if !VERBATIM_FIELDS.contains(&name) {
+            let commands = entry
+                .get(name)
+                .into_iter()
+                .flatten()
+                .filter_map(|chunk| match &chunk.v {
+                    Chunk::Normal(text) | Chunk::Verbatim(text) => Some(text),
+                    Chunk::Math(_) => None,
+                })
+                .flat_map(|text| latex_commands(text))
+                .collect::<Vec<_>>();
+            if !commands.is_empty() {
+                let commands = commands
+                    .iter()
+                    .map(|command| format!("`\\{command}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                warn(
+                    format!(
+                        "`{name}` of entry `{key}` contains unsupported LaTeX commands: {commands}"
+                    ),
+                    field.value.span.start,
+                );
+            }
+        }
+    }
+
+    diagnostics
+}
+
+/// The names of the LaTeX commands in a text.
+fn latex_commands(text: &str) -> Vec<&str> {
+    text.split('\\')
+        .skip(1)
+        .map(|rest| {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            &rest[..end]
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The additional keys of the entries given by their `ids` fields.
+fn aliases(bibliography: &Bibliography) -> Vec<(String, String)> {
+    bibliography
+        .iter()
+        .filter_map(|entry| {
+            let ids = entry.get_as::<Vec<String>>("ids").ok()?;
+            Some(ids.into_iter().map(|id| (id, entry.key.clone())))
+        })
+        .flatten()
+        .collect()
+}
+
+/// Combines the given entries with the macros of all files. The macros of the `i`th file take
+/// precedence, followed by those of the other files in order.
+fn with_macros<'s>(
+    entries: Vec<Spanned<RawEntry<'s>>>,
+    raws: &[RawBibliography<'s>],
+    i: usize,
+) -> RawBibliography<'s> {
+    let others = raws
+        .iter()
+        .enumerate()
+        .filter(|&(j, _)| j != i)
+        .flat_map(|(_, raw)| &raw.abbreviations);
+    let abbreviations: Vec<Pair> = raws[i]
+        .abbreviations
+        .iter()
+        .chain(others)
+        .cloned()
+        .collect();
+    RawBibliography {
+        preamble: String::new(),
+        entries,
+        abbreviations,
+    }
+}
+
+/// The keys of the entries an entry inherits data from via `crossref` and `xdata`, together with
+/// the field referring to them.
+fn parents<'a>(entry: &'a RawEntry) -> Vec<(&'a str, Spanned<String>)> {
+    let mut parents = Vec::new();
+    for field in &entry.fields {
+        let name = field.key.v;
+        let is_list = match name.to_ascii_lowercase().as_str() {
+            "crossref" => false,
+            "xdata" => true,
+            _ => continue,
+        };
+
+        let value = field
+            .value
+            .v
+            .iter()
+            .map(|chunk| match chunk.v {
+                RawChunk::Normal(s) | RawChunk::Abbreviation(s) => s,
+            })
+            .collect::<String>();
+        let keys = if is_list {
+            value.split(',').map(str::trim).collect()
+        } else {
+            vec![value.trim()]
+        };
+        parents.extend(keys.into_iter().filter(|key| !key.is_empty()).map(|key| {
+            (
+                name,
+                Spanned::new(key.to_string(), field.value.span.clone()),
+            )
+        }));
+    }
+    parents
+}
+
+fn parse_error(source: &BiblatexSource, error: ParseError) -> Error {
+    let detail = match &error.kind {
+        ParseErrorKind::UnknownAbbreviation(name) => {
+            format!("`@string` macro `{name}` is not defined in any bibliography")
+        }
+        kind => kind.to_string(),
+    };
+    format_error(
+        ErrorKind::Parse,
+        "failed to parse BibLaTeX",
+        detail,
+        source.path,
+        line_column(source.text, error.span.start),
+    )
+}
+
+fn type_error(source: &BiblatexSource, offset: usize, error: TypeError) -> Error {
+    format_error(
+        ErrorKind::Parse,
+        "failed to parse BibLaTeX",
+        error.kind,
+        source.path,
+        line_column(source.text, offset),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_definitions() {
+        let journals = r#"
+            @string{jacm = "Journal of the ACM"}
+            @xdata{acm, year = {2020}}
+            @proceedings{wok2020, title = {Proceedings of the Wok Conference}}
+        "#;
+        let papers = r#"
+            @article{netwok, title = {Net Wok}, journaltitle = jacm, xdata = {acm}}
+            @inproceedings{dumpling, title = {Dumplings}, crossref = {wok2020}}
+        "#;
+        let sources = [
+            BiblatexSource {
+                path: Some("journals.bib"),
+                text: journals,
+            },
+            BiblatexSource {
+                path: Some("papers.bib"),
+                text: papers,
+            },
+        ];
+        let libraries = from_biblatex_sources(&sources, false).unwrap();
+
+        let keys = libraries[1].library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["netwok", "dumpling"]);
+
+        let article = libraries[1].library.get("netwok").unwrap();
+        let journal = article.parents()[0].title().unwrap();
+        assert_eq!(journal.value.to_str(), "Journal of the ACM");
+        assert!(article.date().is_some());
+
+        let paper = libraries[1].library.get("dumpling").unwrap();
+        let proceedings = paper.parents()[0].title().unwrap();
+        assert_eq!(
+            proceedings.value.to_str(),
+            "Proceedings of the Wok Conference"
+        );
+    }
+
+    #[test]
+    fn test_missing_definitions() {
+        let source = |text| BiblatexSource {
+            path: Some("papers.bib"),
+            text,
+        };
+
+        let err = from_biblatex_sources(&[source("@article{netwok, journaltitle = jacm}")], false)
+            .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::Parse);
+        assert_eq!((err.line, err.column), (Some(1), Some(33)));
+        assert_eq!(
+            err.message,
+            "failed to parse BibLaTeX (papers.bib:1:33: `@string` macro `jacm` is not defined \
+             in any bibliography)"
+        );
+
+        let err =
+            from_biblatex_sources(&[source("@inproceedings{netwok, crossref = {wok}}")], false)
+                .unwrap_err();
+        assert_eq!(
+            err.message,
+            "failed to parse BibLaTeX (papers.bib:1:35: `crossref` of entry `netwok` refers to \
+             `wok`, which is not defined in any bibliography)"
+        );
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        let text = "@article{netwok, title = {Net Wok}}
+@article{unclosed, title = {Dumplings}
+@article{wok, title = {Wok}, year = {soon}}
+@inbook{chapter, title = {Woks}, crossref = {wok}}
+@article{macro, journaltitle = jacm}
+@book{book, title = {The Wok Book}}
+";
+        let sources = [BiblatexSource {
+            path: Some("papers.bib"),
+            text,
+        }];
+
+        let err = from_biblatex_sources(&sources, false).unwrap_err();
+        assert!(err.message.ends_with(" and 3 more errors"));
+        let errors = err
+            .errors
+            .iter()
+            .map(|error| (error.key.as_deref().unwrap(), error.line.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [("unclosed", 2), ("wok", 3), ("chapter", 4), ("macro", 5)]
+        );
+
+        let libraries = from_biblatex_sources(&sources, true).unwrap();
+        let keys = libraries[0].library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["netwok", "book"]);
+        assert_eq!(libraries[0].skipped.len(), 4);
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let text = r#"@article{netwok,
+  titel = {Net Wok},
+  title = {The \emph{Net} Wok, \"Uber $\alpha$},
+  date = {last year},
+  url = {https://example.com/\wok},
+}"#;
+        let sources = [BiblatexSource {
+            path: Some("papers.bib"),
+            text,
+        }];
+        let libraries = from_biblatex_sources(&sources, false).unwrap();
+        let diagnostics = libraries[0]
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.line.unwrap(), diagnostic.message.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            diagnostics,
+            [
+                (2, "unknown field `titel` in entry `netwok` was ignored"),
+                (
+                    3,
+                    "`title` of entry `netwok` contains unsupported LaTeX commands: `\\emph`"
+                ),
+                (
+                    4,
+                    "`date` of entry `netwok` is not a valid date and was ignored: last year"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! An intermediate representation for entries of bibliography formats that Hayagriva doesn't
+//! support natively. Entries are built in the shape of Hayagriva's YAML format and then
+//! deserialized by Hayagriva, so that they are subject to the same rules as YAML files.
+
+use std::collections::HashSet;
+
+use hayagriva::Library;
+use serde_yaml::{Mapping, Value};
+
+/// A bibliography entry in the shape of Hayagriva's YAML format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryBuilder {
+    fields: Mapping,
+    parent: Option<Box<EntryBuilder>>,
+}
+
+impl EntryBuilder {
+    /// Creates an entry of the given Hayagriva entry type.
+    pub fn new(entry_type: &str) -> Self {
+        let mut fields = Mapping::new();
+        fields.insert("type".into(), entry_type.into());
+        Self {
+            fields,
+            parent: None,
+        }
+    }
+
+    /// Checks whether the given field was set.
+    pub fn has(&self, field: &str) -> bool {
+        self.fields.contains_key(field)
+    }
+
+    /// Sets a field to the given value, replacing any previous value.
+    pub fn set(&mut self, field: &str, value: impl Into<Value>) {
+        self.fields.insert(field.into(), value.into());
+    }
+
+    /// Sets a field to the given text, unless the text is blank.
+    pub fn set_text(&mut self, field: &str, value: &str) {
+        let value = value.trim();
+        if !value.is_empty() {
+            self.set(field, value);
+        }
+    }
+
+    /// Sets the title and optionally its short form, unless the title is blank.
+    pub fn set_title(&mut self, title: &str, short: Option<&str>) {
+        let title = title.trim();
+        if title.is_empty() {
+            return;
+        }
+
+        match short.map(str::trim).filter(|short| !short.is_empty()) {
+            Some(short) => {
+                let mut value = Mapping::new();
+                value.insert("value".into(), title.into());
+                value.insert("short".into(), short.into());
+                self.set("title", value);
+            }
+            None => self.set("title", title),
+        }
+    }
+
+    /// Sets the publisher and its location. If only the location is known, it is set as the
+    /// entry's location.
+    pub fn set_publisher(&mut self, name: Option<&str>, location: Option<&str>) {
+        let name = name.map(str::trim).filter(|name| !name.is_empty());
+        let location = location
+            .map(str::trim)
+            .filter(|location| !location.is_empty());
+        match (name, location) {
+            (Some(name), Some(location)) => {
+                let mut value = Mapping::new();
+                value.insert("name".into(), name.into());
+                value.insert("location".into(), location.into());
+                self.set("publisher", value);
+            }
+            (Some(name), None) => self.set("publisher", name),
+            (None, Some(location)) => self.set("location", location),
+            (None, None) => {}
+        }
+    }
+
+    /// Sets the language, unless it is not a well-formed language tag such as `en-US`.
+    pub fn set_language(&mut self, value: &str) {
+        let value = value.trim();
+        let well_formed = !value.is_empty()
+            && value.split(['-', '_']).all(|part| {
+                (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+            });
+        if well_formed {
+            self.set("language", value);
+        }
+    }
+
+    /// Appends a value to a list field such as `author`.
+    pub fn push(&mut self, field: &str, value: impl Into<Value>) {
+        let list = self
+            .fields
+            .entry(field.into())
+            .or_insert_with(|| Value::Sequence(Vec::new()));
+        if let Value::Sequence(list) = list {
+            list.push(value.into());
+        }
+    }
+
+    /// Adds a person with a role other than author or editor, such as `translator`.
+    pub fn push_affiliated(&mut self, role: &str, person: Value) {
+        let affiliated = self
+            .fields
+            .entry("affiliated".into())
+            .or_insert_with(|| Value::Sequence(Vec::new()));
+        let Value::Sequence(affiliated) = affiliated else {
+            return;
+        };
+
+        let existing = affiliated
+            .iter_mut()
+            .find(|group| group.get("role").and_then(Value::as_str) == Some(role));
+        match existing.and_then(|group| group.get_mut("names")) {
+            Some(Value::Sequence(names)) => names.push(person),
+            _ => {
+                let mut group = Mapping::new();
+                group.insert("role".into(), role.into());
+                group.insert("names".into(), Value::Sequence(vec![person]));
+                affiliated.push(Value::Mapping(group));
+            }
+        }
+    }
+
+    /// Sets a serial number such as `doi`, `isbn`, or `pmid`, unless it is blank.
+    pub fn set_serial_number(&mut self, kind: &str, value: &str) {
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+
+        let serial = self
+            .fields
+            .entry("serial-number".into())
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if let Value::Mapping(serial) = serial {
+            serial.insert(kind.into(), value.into());
+        }
+    }
+
+    /// Sets the URL and optionally the date at which it was last accessed.
+    pub fn set_url(&mut self, url: &str, accessed: Option<String>) {
+        let url = url.trim();
+        if url.is_empty() {
+            return;
+        }
+
+        match accessed {
+            Some(date) => {
+                let mut value = Mapping::new();
+                value.insert("value".into(), url.into());
+                value.insert("date".into(), date.into());
+                self.set("url", value);
+            }
+            None => self.set("url", url),
+        }
+    }
+
+    /// Returns the parent of this entry, creating one of the given type if necessary.
+    pub fn parent(&mut self, entry_type: &str) -> &mut EntryBuilder {
+        self.parent
+            .get_or_insert_with(|| Box::new(EntryBuilder::new(entry_type)))
+    }
+
+    /// Returns the parent of this entry if one was created.
+    pub fn existing_parent(&mut self) -> Option<&mut EntryBuilder> {
+        self.parent.as_deref_mut()
+    }
+
+    /// Converts the entry into its YAML representation.
+    pub fn into_value(self) -> Value {
+        let Self { mut fields, parent } = self;
+        if let Some(parent) = parent {
+            fields.insert("parent".into(), parent.into_value());
+        }
+        Value::Mapping(fields)
+    }
+}
+
+/// Creates a person in the shape of Hayagriva's YAML format.
+pub fn person(
+    name: &str,
+    given_name: Option<&str>,
+    prefix: Option<&str>,
+    suffix: Option<&str>,
+) -> Value {
+    let mut person = Mapping::new();
+    person.insert("name".into(), name.trim().into());
+    for (field, value) in [
+        ("given-name", given_name),
+        ("prefix", prefix),
+        ("suffix", suffix),
+    ] {
+        if let Some(value) = value.map(str::trim).filter(|value| !value.is_empty()) {
+            person.insert(field.into(), value.into());
+        }
+    }
+    Value::Mapping(person)
+}
+
+/// Creates a person from a name in one of the forms `Family, Given`, `Family, Given, Suffix`,
+/// or just `Family`.
+pub fn person_from_str(name: &str) -> Value {
+    let parts = name.split(',').map(str::trim).collect::<Vec<_>>();
+    match parts.as_slice() {
+        [family, given, suffix] => person(family, Some(given), None, Some(suffix)),
+        [family, given] => person(family, Some(given), None, None),
+        _ => person(name, None, None, None),
+    }
+}
+
+/// Formats a date in the shape of Hayagriva's YAML format.
+pub fn date(year: i32, month: Option<u8>, day: Option<u8>) -> String {
+    match (month, day) {
+        (Some(month), Some(day)) => format!("{year:04}-{month:02}-{day:02}"),
+        (Some(month), None) => format!("{year:04}-{month:02}"),
+        _ => format!("{year:04}"),
+    }
+}
+
+/// Parses a loosely formatted date such as `2020`, `2020-03-04`, `2020/03/04/`, or
+/// `2020 Mar 4`. Anything after the recognized date parts is ignored.
+pub fn date_from_str(value: &str) -> Option<String> {
+    let mut parts = value
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty());
+
+    let year = parts.next()?;
+    if year.len() != 4 {
+        return None;
+    }
+    let year = year.parse().ok()?;
+
+    let month = parts.next().and_then(|month| {
+        let month = month.parse().ok().or_else(|| month_from_name(month))?;
+        (1..=12).contains(&month).then_some(month)
+    });
+    let day = month
+        .and(parts.next())
+        .and_then(|day| day.parse().ok())
+        .filter(|day| (1..=31).contains(day));
+
+    Some(date(year, month, day))
+}
+
+fn month_from_name(name: &str) -> Option<u8> {
+    const MONTHS: [&str; 12] = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+
+    let prefix = name.get(..3)?.to_ascii_lowercase();
+    let index = MONTHS.iter().position(|month| *month == prefix)?;
+    Some(index as u8 + 1)
+}
+
+/// Converts the entries into a library by deserializing them the same way as Hayagriva YAML.
+pub fn into_library(
+    entries: impl IntoIterator<Item = (String, EntryBuilder)>,
+) -> Result<Library, serde_yaml::Error> {
+    let mut library = Mapping::new();
+    for (key, entry) in entries {
+        if library.contains_key(key.as_str()) {
+            return Err(serde::de::Error::custom(format!("duplicate key {key}")));
+        }
+        library.insert(key.into(), entry.into_value());
+    }
+    serde_yaml::from_value(Value::Mapping(library))
+}
+
+/// Generates predictable keys for entries of formats that don't carry citation keys.
+///
+/// Keys have the form `{name}{year}`, where the name is usually the family name of the first
+/// author, e.g. `smith2020`. If that key is already taken, the letters `a`, `b`, ... are appended
+/// in order of generation, e.g. `smith2020a`.
+#[derive(Debug, Clone, Default)]
+pub struct KeyGenerator {
+    used: HashSet<String>,
+}
+
+impl KeyGenerator {
+    /// Creates a key generator that avoids the given explicitly assigned keys.
+    pub fn new<'a>(reserved: impl IntoIterator<Item = &'a str>) -> Self {
+        let used = reserved.into_iter().map(str::to_string).collect();
+        Self { used }
+    }
+
+    /// Generates a new key from a name and the year at the start of a date.
+    pub fn generate(&mut self, name: Option<&str>, date: Option<&str>) -> String {
+        let name = name
+            .unwrap_or("entry")
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect::<String>();
+        let year = date
+            .and_then(|date| date.trim().get(..4))
+            .filter(|year| year.bytes().all(|b| b.is_ascii_digit()))
+            .unwrap_or_default();
+
+        let base = format!("{name}{year}");
+        let mut key = base.clone();
+        let mut n = 0;
+        while self.used.contains(&key) {
+            key = format!("{base}{}", letters(n));
+            n += 1;
+        }
+        self.used.insert(key.clone());
+        key
+    }
+}
+
+/// Converts a number into the sequence `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn letters(mut n: usize) -> String {
+    let mut result = Vec::new();
+    loop {
+        result.push(b'a' + (n % 26) as u8);
+        if n < 26 {
+            break;
+        }
+        n = n / 26 - 1;
+    }
+    result.reverse();
+    String::from_utf8(result).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys() {
+        let mut keys = KeyGenerator::new(["smith2020"]);
+        assert_eq!(keys.generate(Some("Smith"), Some("2020-03")), "smith2020a");
+        assert_eq!(keys.generate(Some("Smith"), Some("2020")), "smith2020b");
+        assert_eq!(keys.generate(Some("van Dyke"), None), "vandyke");
+        assert_eq!(letters(25), "z");
+        assert_eq!(letters(26), "aa");
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(date_from_str("2020/03/04/").as_deref(), Some("2020-03-04"));
+        assert_eq!(date_from_str("2020 Mar 4").as_deref(), Some("2020-03-04"));
+        assert_eq!(date_from_str("2020 Spring").as_deref(), Some("2020"));
+        assert_eq!(date_from_str("March 2020"), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Decoding of `CITATION.cff` files, which describe how to cite a piece of software.
+//! See https://github.com/citation-file-format/citation-file-format
+
+use hayagriva::Library;
+use serde_yaml::Value;
+
+use super::builder::{self, EntryBuilder, KeyGenerator};
+use super::DecodeError;
+
+/// Parses a `CITATION.cff` file into a library.
+///
+/// The file describes the software (or dataset) itself, and optionally a `preferred-citation`,
+/// e.g. a paper describing the software. Both become entries: the software is keyed by its title,
+/// e.g. `numpy`, the preferred citation by [`KeyGenerator`]'s usual scheme, e.g. `harris2020`.
+pub fn from_cff_str(data: &str) -> Result<Library, DecodeError> {
+    let root: Value = serde_yaml::from_str(data)?;
+    if !root.is_mapping() {
+        return Err(DecodeError::new("expected a mapping of CFF keys"));
+    }
+    let title = text(&root, "title").ok_or_else(|| DecodeError::new("missing `title`"))?;
+
+    let mut keys = KeyGenerator::default();
+    let mut entries = Vec::new();
+
+    let key = keys.generate(Some(&title), None);
+    entries.push((key, convert_software(&root)));
+
+    if let Some(preferred) = root.get("preferred-citation") {
+        let name = first_family_name(preferred).or_else(|| text(preferred, "title"));
+        let year = text(preferred, "year")
+            .or_else(|| text(preferred, "date-published"))
+            .or_else(|| text(preferred, "date-released"));
+        let key = keys.generate(name.as_deref(), year.as_deref());
+        entries.push((key, convert_reference(preferred)));
+    }
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Converts the software described by the top level of the file, or by a reference of type
+/// `software`.
+fn convert_software(value: &Value) -> EntryBuilder {
+    let mut entry = EntryBuilder::new("repository");
+    set_common(&mut entry, value);
+
+    if let Some(date) = text(value, "date-released").and_then(|date| builder::date_from_str(&date))
+    {
+        entry.set("date", date);
+    }
+    if let Some(version) = text(value, "version") {
+        entry.set_serial_number("version", &version);
+    }
+
+    // the repository is the most useful link for software
+    let url = [
+        "repository-code",
+        "url",
+        "repository",
+        "repository-artifact",
+    ]
+    .into_iter()
+    .find_map(|key| text(value, key));
+    if let Some(url) = url {
+        entry.set_url(&url, None);
+    }
+
+    entry
+}
+
+/// Maps a CFF reference type to a Hayagriva entry type and, if applicable, the type of its
+/// container.
+fn entry_types(kind: &str) -> (&'static str, Option<&'static str>) {
+    match kind {
+        "article" | "magazine-article" => ("article", Some("periodical")),
+        "newspaper-article" => ("article", Some("newspaper")),
+        "conference-paper" => ("article", Some("proceedings")),
+        "blog" => ("article", Some("blog")),
+        "chapter" => ("chapter", Some("book")),
+        "book" => ("book", None),
+        "edited-work" => ("anthology", None),
+        "proceedings" | "conference" => ("proceedings", None),
+        "report" | "standard" | "government-document" => ("report", None),
+        "thesis" => ("thesis", None),
+        "manual" | "encyclopedia" | "dictionary" => ("reference", None),
+        "website" => ("web", None),
+        "unpublished" => ("manuscript", None),
+        "patent" => ("patent", None),
+        "legal-case" => ("case", None),
+        "statute" => ("legislation", None),
+        "video" => ("video", None),
+        "sound-recording" | "music" => ("audio", None),
+        "art" => ("artwork", None),
+        _ => ("misc", None),
+    }
+}
+
+/// Converts a CFF reference such as the `preferred-citation`.
+fn convert_reference(value: &Value) -> EntryBuilder {
+    let kind = text(value, "type").unwrap_or_default();
+    if kind.starts_with("software") || kind == "data" {
+        return convert_software(value);
+    }
+
+    let (entry_type, container_type) = entry_types(&kind);
+    let mut entry = EntryBuilder::new(entry_type);
+    set_common(&mut entry, value);
+
+    for person in persons(value, "editors") {
+        entry.push("editor", person);
+    }
+    for person in persons(value, "translators") {
+        entry.push_affiliated("translator", person);
+    }
+
+    let date = match (text(value, "year"), text(value, "month")) {
+        (Some(year), Some(month)) => builder::date_from_str(&format!("{year}-{month}")),
+        (Some(year), None) => builder::date_from_str(&year),
+        _ => None,
+    };
+    if let Some(date) = date
+        .or_else(|| text(value, "date-published").and_then(|date| builder::date_from_str(&date)))
+    {
+        entry.set("date", date);
+    }
+    if let Some(url) = text(value, "url").or_else(|| text(value, "repository-code")) {
+        entry.set_url(&url, None);
+    }
+
+    for (key, kind) in [("isbn", "isbn"), ("issn", "issn"), ("version", "version")] {
+        if let Some(number) = text(value, key) {
+            entry.set_serial_number(kind, &number);
+        }
+    }
+    match (text(value, "start"), text(value, "end")) {
+        (Some(start), Some(end)) => entry.set_text("page-range", &format!("{start}-{end}")),
+        (Some(start), None) => entry.set_text("page-range", &start),
+        _ => {}
+    }
+    if let Some(pages) = text(value, "pages").and_then(|pages| pages.parse::<u32>().ok()) {
+        entry.set("page-total", pages);
+    }
+    if let Some(edition) = text(value, "edition") {
+        entry.set_text("edition", &edition);
+    }
+
+    let publisher = value.get("publisher");
+    entry.set_publisher(
+        publisher
+            .and_then(|publisher| text(publisher, "name"))
+            .as_deref(),
+        publisher
+            .and_then(|publisher| text(publisher, "city"))
+            .as_deref(),
+    );
+
+    // volume and issue describe the container if there is one, the entry itself otherwise
+    let container_title = text(value, "journal")
+        .or_else(|| text(value, "collection-title"))
+        .or_else(|| {
+            value
+                .get("conference")
+                .and_then(|conference| text(conference, "name"))
+        });
+    let target = match (container_type, &container_title) {
+        (Some(container_type), Some(container_title)) => {
+            let parent = entry.parent(container_type);
+            parent.set_text("title", container_title);
+            parent
+        }
+        _ => &mut entry,
+    };
+    for key in ["volume", "issue"] {
+        if let Some(value) = text(value, key) {
+            target.set_text(key, &value);
+        }
+    }
+
+    entry
+}
+
+/// Sets the fields shared by software and other references.
+fn set_common(entry: &mut EntryBuilder, value: &Value) {
+    if let Some(title) = text(value, "title") {
+        entry.set_text("title", &title);
+    }
+    for person in persons(value, "authors") {
+        entry.push("author", person);
+    }
+    if let Some(doi) = doi(value) {
+        entry.set_serial_number("doi", &doi);
+    }
+    if let Some(abstract_) = text(value, "abstract") {
+        entry.set_text("abstract", &abstract_);
+    }
+}
+
+/// Returns the DOI, either from the `doi` key or the first DOI in `identifiers`.
+fn doi(value: &Value) -> Option<String> {
+    text(value, "doi").or_else(|| {
+        value
+            .get("identifiers")?
+            .as_sequence()?
+            .iter()
+            .filter(|identifier| text(identifier, "type").as_deref() == Some("doi"))
+            .find_map(|identifier| text(identifier, "value"))
+    })
+}
+
+/// Reads a string or number.
+fn text(value: &Value, key: &str) -> Option<String> {
+    match value.get(key)? {
+        Value::String(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Reads a list of CFF persons and entities as Hayagriva persons.
+fn persons(value: &Value, key: &str) -> Vec<Value> {
+    let Some(persons) = value.get(key).and_then(Value::as_sequence) else {
+        return Vec::new();
+    };
+
+    persons
+        .iter()
+        .filter_map(|person| {
+            let part = |key| text(person, key);
+            if let Some(family) = part("family-names") {
+                Some(builder::person(
+                    &family,
+                    part("given-names").as_deref(),
+                    part("name-particle").as_deref(),
+                    part("name-suffix").as_deref(),
+                ))
+            } else {
+                // entities such as organizations only have a name
+                part("name").map(|name| builder::person(&name, None, None, None))
+            }
+        })
+        .collect()
+}
+
+fn first_family_name(value: &Value) -> Option<String> {
+    let first = value.get("authors")?.as_sequence()?.first()?;
+    text(first, "family-names").or_else(|| text(first, "name"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_citation_file() {
+        let data = r#"
+cff-version: 1.2.0
+message: If you use this software, please cite it as below.
+title: Net Wok
+version: 2.0.4
+doi: 10.5281/zenodo.1234
+date-released: 2021-08-11
+repository-code: https://github.com/example/net-wok
+authors:
+  - family-names: Astley
+    given-names: Rick
+  - name: The Dumpling Collective
+preferred-citation:
+  type: article
+  title: At-scale impact of the Net Wok
+  authors:
+    - family-names: Astley
+      given-names: Rick
+  journal: Armenian Journal of Proceedings
+  volume: 61
+  start: 192
+  end: 219
+  year: 2020
+  doi: 10.1000/netwok
+"#;
+        let library = from_cff_str(data).unwrap();
+
+        let keys = library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["netwok", "astley2020"]);
+
+        let software = library.get("netwok").unwrap();
+        assert_eq!(
+            software.entry_type(),
+            &hayagriva::types::EntryType::Repository
+        );
+        assert_eq!(software.authors().unwrap().len(), 2);
+        assert_eq!(software.keyed_serial_number("version"), Some("2.0.4"));
+        assert_eq!(software.doi(), Some("10.5281/zenodo.1234"));
+        assert_eq!(software.date().unwrap().day, Some(10));
+        assert_eq!(
+            software.url().unwrap().value.as_str(),
+            "https://github.com/example/net-wok"
+        );
+
+        let article = library.get("astley2020").unwrap();
+        assert_eq!(article.doi(), Some("10.1000/netwok"));
+        assert!(article.page_range().is_some());
+        assert!(article.parents()[0].volume().is_some());
+    }
+}
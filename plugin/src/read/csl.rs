@@ -0,0 +1,337 @@
+//! Decoding of CSL-JSON, the format exported by Zotero, Mendeley, and most other reference
+//! managers. See https://citeproc-js.readthedocs.io/en/latest/csl-json/markup.html
+
+use hayagriva::Library;
+use serde_json::{Map, Value};
+
+use super::builder::{self, EntryBuilder};
+use super::DecodeError;
+
+type Item = Map<String, Value>;
+
+/// Converts a parsed CSL-JSON array of items (or a single item) into a library.
+pub fn from_csl_json(value: Value) -> Result<Library, DecodeError> {
+    let items = match value {
+        Value::Array(items) => items,
+        item @ Value::Object(_) => vec![item],
+        _ => return Err(DecodeError::new("expected an array of CSL-JSON items")),
+    };
+
+    let entries = items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let Value::Object(item) = item else {
+                return Err(DecodeError::new(format!("item {i} is not an object")));
+            };
+            let key = text(&item, "id")
+                .ok_or_else(|| DecodeError::new(format!("item {i} has no `id`")))?;
+            Ok((key, convert_item(&item)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Maps a CSL item type to a Hayagriva entry type and, if applicable, the type of its container.
+fn entry_types(kind: &str) -> (&'static str, Option<&'static str>) {
+    match kind {
+        "article-journal" | "article-magazine" | "article" | "review" | "review-book" => {
+            ("article", Some("periodical"))
+        }
+        "article-newspaper" => ("article", Some("newspaper")),
+        "paper-conference" => ("article", Some("proceedings")),
+        "post-weblog" => ("article", Some("blog")),
+        "chapter" => ("chapter", Some("book")),
+        "entry" | "entry-dictionary" | "entry-encyclopedia" => ("entry", Some("reference")),
+        "book" | "classic" => ("book", None),
+        "collection" => ("anthology", None),
+        "periodical" => ("periodical", None),
+        "report" | "standard" => ("report", None),
+        "thesis" => ("thesis", None),
+        "manuscript" => ("manuscript", None),
+        "webpage" => ("web", None),
+        "post" => ("post", None),
+        "dataset" | "software" => ("repository", None),
+        "patent" => ("patent", None),
+        "legal_case" => ("case", None),
+        "legislation" | "bill" | "regulation" | "treaty" => ("legislation", None),
+        "motion_picture" | "broadcast" => ("video", None),
+        "song" | "musical_score" => ("audio", None),
+        "graphic" | "figure" | "map" => ("artwork", None),
+        "event" => ("exhibition", None),
+        "performance" | "speech" => ("performance", None),
+        _ => ("misc", None),
+    }
+}
+
+/// Maps CSL name variables other than author and editor to Hayagriva person roles.
+const ROLES: &[(&str, &str)] = &[
+    ("translator", "translator"),
+    ("compiler", "compiler"),
+    ("composer", "composer"),
+    ("director", "director"),
+    ("illustrator", "illustrator"),
+    ("narrator", "narrator"),
+    ("organizer", "organizer"),
+    ("performer", "cast-member"),
+    ("producer", "producer"),
+    ("executive-producer", "executive-producer"),
+    ("script-writer", "writer"),
+];
+
+fn convert_item(item: &Item) -> EntryBuilder {
+    let kind = text(item, "type").unwrap_or_default();
+    let (entry_type, container_type) = entry_types(&kind);
+    let mut entry = EntryBuilder::new(entry_type);
+
+    if let Some(title) = text(item, "title") {
+        let short = text(item, "title-short").or_else(|| text(item, "shortTitle"));
+        entry.set_title(&title, short.as_deref());
+    }
+
+    for person in names(item, "author") {
+        entry.push("author", person);
+    }
+    for person in names(item, "editor") {
+        entry.push("editor", person);
+    }
+    for (variable, role) in ROLES {
+        for person in names(item, variable) {
+            entry.push_affiliated(role, person);
+        }
+    }
+
+    if let Some(date) = date(item, "issued") {
+        entry.set("date", date);
+    }
+    if let Some(url) = text(item, "URL") {
+        entry.set_url(&url, date(item, "accessed"));
+    }
+
+    for (variable, kind) in [
+        ("DOI", "doi"),
+        ("ISBN", "isbn"),
+        ("ISSN", "issn"),
+        ("PMID", "pmid"),
+        ("PMCID", "pmcid"),
+        ("version", "version"),
+    ] {
+        if let Some(value) = text(item, variable) {
+            entry.set_serial_number(kind, &value);
+        }
+    }
+
+    for (variable, field) in [
+        ("page", "page-range"),
+        ("edition", "edition"),
+        ("chapter-number", "chapter"),
+        ("abstract", "abstract"),
+        ("note", "note"),
+        ("genre", "genre"),
+        ("archive", "archive"),
+        ("archive_location", "archive-location"),
+        ("call-number", "call-number"),
+    ] {
+        if let Some(value) = text(item, variable) {
+            entry.set_text(field, &value);
+        }
+    }
+    // these fields don't accept free-form text
+    for (variable, field) in [
+        ("number-of-pages", "page-total"),
+        ("number-of-volumes", "volume-total"),
+    ] {
+        if let Some(value) = text(item, variable).and_then(|value| value.parse::<u32>().ok()) {
+            entry.set(field, value);
+        }
+    }
+
+    if let Some(language) = text(item, "language") {
+        entry.set_language(&language);
+    }
+
+    entry.set_publisher(
+        text(item, "publisher").as_deref(),
+        text(item, "publisher-place").as_deref(),
+    );
+
+    // volume and issue describe the container if there is one, the entry itself otherwise
+    let container_title = text(item, "container-title");
+    let target = match (container_type, &container_title) {
+        (Some(container_type), Some(container_title)) => {
+            let parent = entry.parent(container_type);
+            parent.set_text("title", container_title);
+            for person in names(item, "container-author") {
+                parent.push("author", person);
+            }
+            parent
+        }
+        _ => &mut entry,
+    };
+    for field in ["volume", "issue"] {
+        if let Some(value) = text(item, field) {
+            target.set_text(field, &value);
+        }
+    }
+    match text(item, "number") {
+        Some(number) if matches!(entry_type, "report" | "patent" | "legislation" | "case") => {
+            target.set_serial_number("serial", &number);
+        }
+        Some(number) if !target.has("issue") => target.set_text("issue", &number),
+        _ => {}
+    }
+
+    if let Some(event) = text(item, "event-title").or_else(|| text(item, "event")) {
+        let conference = match entry.existing_parent() {
+            Some(parent) => parent.parent("conference"),
+            None => entry.parent("conference"),
+        };
+        conference.set_text("title", &event);
+        if let Some(place) = text(item, "event-place") {
+            conference.set_text("location", &place);
+        }
+    }
+
+    entry
+}
+
+/// Reads a string or number variable.
+fn text(item: &Item, variable: &str) -> Option<String> {
+    match item.get(variable)? {
+        Value::String(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Reads a name variable as a list of Hayagriva persons.
+fn names(item: &Item, variable: &str) -> Vec<serde_yaml::Value> {
+    let Some(Value::Array(names)) = item.get(variable) else {
+        return Vec::new();
+    };
+
+    names
+        .iter()
+        .filter_map(Value::as_object)
+        .filter_map(|name| {
+            let part = |key| name.get(key).and_then(Value::as_str);
+            if let Some(family) = part("family") {
+                let prefix = part("non-dropping-particle").or(part("dropping-particle"));
+                Some(builder::person(
+                    family,
+                    part("given"),
+                    prefix,
+                    part("suffix"),
+                ))
+            } else {
+                part("literal").map(|literal| builder::person(literal, None, None, None))
+            }
+        })
+        .collect()
+}
+
+/// Reads a date variable, either from its `date-parts`, or by parsing its `raw` or `literal`
+/// representation. Date ranges are reduced to their start.
+pub(super) fn date(item: &Item, variable: &str) -> Option<String> {
+    let Value::Object(date) = item.get(variable)? else {
+        return None;
+    };
+
+    if let Some(Value::Array(ranges)) = date.get("date-parts") {
+        let parts = ranges
+            .first()
+            .and_then(Value::as_array)
+            .map(|parts| {
+                parts
+                    .iter()
+                    .map_while(|part| match part {
+                        Value::Number(part) => part.as_i64(),
+                        Value::String(part) => part.trim().parse().ok(),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        if let [year, rest @ ..] = parts.as_slice() {
+            // seasons (13 to 24) and other values out of range are left out, like in `raw` dates
+            let month = rest
+                .first()
+                .and_then(|&month| u8::try_from(month).ok())
+                .filter(|month| (1..=12).contains(month));
+            let day = month
+                .and(rest.get(1))
+                .and_then(|&day| u8::try_from(day).ok())
+                .filter(|day| (1..=31).contains(day));
+            return Some(builder::date(*year as i32, month, day));
+        }
+    }
+
+    ["raw", "literal"]
+        .into_iter()
+        .filter_map(|key| date.get(key).and_then(Value::as_str))
+        .find_map(builder::date_from_str)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zotero_export() {
+        let data = r#"[
+            {
+                "id": "netwok",
+                "type": "article-journal",
+                "title": "At-scale impact of the Net Wok",
+                "container-title": "Armenian Journal of Proceedings",
+                "volume": "61",
+                "page": "192-219",
+                "DOI": "10.1000/netwok",
+                "author": [
+                    {"family": "Astley", "given": "Rick"},
+                    {"family": "Morris", "given": "Linda"}
+                ],
+                "issued": {"date-parts": [[2020, 3]]}
+            }
+        ]"#;
+        let library = from_csl_json(serde_json::from_str(data).unwrap()).unwrap();
+        let entry = library.get("netwok").unwrap();
+
+        assert_eq!(entry.entry_type(), &hayagriva::types::EntryType::Article);
+        assert_eq!(entry.authors().unwrap().len(), 2);
+        assert_eq!(entry.date().unwrap().month, Some(2));
+        assert_eq!(entry.doi(), Some("10.1000/netwok"));
+        let parent = &entry.parents()[0];
+        assert_eq!(
+            parent.entry_type(),
+            &hayagriva::types::EntryType::Periodical
+        );
+        assert!(parent.volume().is_some());
+    }
+
+    #[test]
+    fn test_date_parts_out_of_range() {
+        let data = r#"[
+            {"id": "spring", "type": "book", "issued": {"date-parts": [[2020, 21]]}},
+            {"id": "zero", "type": "book", "issued": {"date-parts": [[2020, 0, 5]]}},
+            {"id": "overflow", "type": "book", "issued": {"date-parts": [[2020, 3, 32]]}}
+        ]"#;
+        let library = from_csl_json(serde_json::from_str(data).unwrap()).unwrap();
+
+        for key in ["spring", "zero"] {
+            let date = library.get(key).unwrap().date().unwrap();
+            assert_eq!((date.year, date.month, date.day), (2020, None, None));
+        }
+        let date = library.get("overflow").unwrap().date().unwrap();
+        assert_eq!((date.year, date.month, date.day), (2020, Some(2), None));
+    }
+
+    #[test]
+    fn test_missing_id() {
+        let value = serde_json::from_str(r#"[{"type": "book"}]"#).unwrap();
+        let err = from_csl_json(value).unwrap_err();
+        assert_eq!(err.message, "item 0 has no `id`");
+    }
+}
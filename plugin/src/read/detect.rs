@@ -0,0 +1,161 @@
+//! Detection of a bibliography's format from its file extension or contents.
+
+use crate::model::Format;
+
+use super::doi;
+
+/// Returns the format implied by a file extension. Extensions shared by several formats, such as
+/// `.json` and `.xml`, don't determine a format; their contents need to be inspected.
+pub fn from_extension(ext: &str) -> Option<Format> {
+    let format = match ext.to_lowercase().as_str() {
+        "yml" | "yaml" => Format::Hayagriva,
+        "bib" => Format::Biblatex,
+        "ris" => Format::Ris,
+        "nbib" => Format::Medline,
+        "cff" => Format::Cff,
+        _ => return None,
+    };
+    Some(format)
+}
+
+/// Detects the format of a bibliography from its contents.
+pub fn detect(data: &str) -> Option<Format> {
+    let data = data.trim_start_matches('\u{feff}').trim_start();
+
+    // the tagged formats start with the tag that opens a record
+    if data.starts_with("TY  -") {
+        return Some(Format::Ris);
+    }
+    if data.starts_with("PMID-") {
+        return Some(Format::Medline);
+    }
+
+    if data.starts_with('<') {
+        return match xml_root(data)? {
+            "xml" | "records" | "record" => Some(Format::Endnote),
+            "modsCollection" | "mods" => Some(Format::Mods),
+            "ref-list" | "back" | "article" => Some(Format::Jats),
+            _ => None,
+        };
+    }
+
+    if data.starts_with(['[', '{']) {
+        // if it's not valid JSON, report the errors as CSL-JSON, the most common JSON format
+        let format = match serde_json::from_str(data) {
+            Ok(value) if doi::is_doi_metadata(&value) => Format::Crossref,
+            _ => Format::CslJson,
+        };
+        return Some(format);
+    }
+
+    // CITATION.cff files are YAML, but always declare their version
+    if data.lines().any(|line| line.starts_with("cff-version:")) {
+        return Some(Format::Cff);
+    }
+
+    // a Hayagriva file starts with the key of its first entry, even if one of its fields contains
+    // text that looks like a BibLaTeX entry; BibLaTeX allows arbitrary text between entries, so
+    // look for any line starting an entry
+    let first = data
+        .lines()
+        .map(str::trim_end)
+        .find(|line| !line.is_empty() && !line.starts_with('#') && *line != "---")?;
+    if is_yaml_key(first) {
+        return Some(Format::Hayagriva);
+    }
+    if data.lines().any(is_biblatex_entry) {
+        return Some(Format::Biblatex);
+    }
+
+    None
+}
+
+/// Returns the local name of the root element of an XML document, skipping the XML declaration,
+/// comments and the doctype.
+fn xml_root(mut data: &str) -> Option<&str> {
+    loop {
+        data = data.trim_start();
+        if let Some(rest) = data.strip_prefix("<?") {
+            data = &rest[rest.find("?>")? + 2..];
+        } else if let Some(rest) = data.strip_prefix("<!--") {
+            data = &rest[rest.find("-->")? + 3..];
+        } else if let Some(rest) = data.strip_prefix("<!") {
+            data = &rest[rest.find('>')? + 1..];
+        } else {
+            break;
+        }
+    }
+
+    let rest = data.strip_prefix('<')?;
+    let end = rest.find(|c: char| c.is_whitespace() || c == '>' || c == '/')?;
+    let name = &rest[..end];
+    Some(name.rsplit(':').next().unwrap_or(name))
+}
+
+/// Whether the line starts a BibLaTeX entry such as `@article{`.
+fn is_biblatex_entry(line: &str) -> bool {
+    let Some(entry) = line.trim_start().strip_prefix('@') else {
+        return false;
+    };
+    let rest = entry.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let has_type = rest.len() < entry.len();
+    has_type && rest.trim_start().starts_with(['{', '('])
+}
+
+/// Whether the line is a top-level YAML mapping key such as `key:`.
+fn is_yaml_key(line: &str) -> bool {
+    if line.starts_with(char::is_whitespace) {
+        return false;
+    }
+    match line.split_once(':') {
+        Some((key, value)) => !key.is_empty() && (value.is_empty() || value.starts_with(' ')),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let cases = [
+            (
+                "netwok:\n  type: article\n  title: Net Wok\n",
+                Some(Format::Hayagriva),
+            ),
+            (
+                "% comment\n\n@article{netwok,\n  title={Net Wok},\n}\n",
+                Some(Format::Biblatex),
+            ),
+            (
+                r#"[{"id": "netwok", "type": "book"}]"#,
+                Some(Format::CslJson),
+            ),
+            (
+                r#"{"message": {"DOI": "10.1000/netwok"}}"#,
+                Some(Format::Crossref),
+            ),
+            ("TY  - JOUR\nER  - \n", Some(Format::Ris)),
+            ("PMID- 12345678\n", Some(Format::Medline)),
+            (
+                "<?xml version=\"1.0\"?>\n<xml><records/></xml>",
+                Some(Format::Endnote),
+            ),
+            ("<mods:modsCollection/>", Some(Format::Mods)),
+            (
+                "<!DOCTYPE article>\n<article><back/></article>",
+                Some(Format::Jats),
+            ),
+            (
+                "netwok:\n  type: article\n  abstract: |\n    @article{netwok,\n",
+                Some(Format::Hayagriva),
+            ),
+            ("cff-version: 1.2.0\ntitle: Net Wok\n", Some(Format::Cff)),
+            ("just some text", None),
+        ];
+        for (data, format) in cases {
+            assert_eq!(detect(data), format, "{data}");
+        }
+    }
+}
@@ -0,0 +1,489 @@
+//! Decoding of DOI metadata in the JSON formats of the Crossref and DataCite REST APIs, e.g. from
+//! `https://api.crossref.org/works/{doi}` or `https://api.datacite.org/dois/{doi}`.
+
+use std::collections::HashMap;
+
+use hayagriva::Library;
+use serde_json::{Map, Value};
+
+use super::builder::{self, EntryBuilder};
+use super::{csl, DecodeError};
+
+type Item = Map<String, Value>;
+
+/// A single work, described by one of the two registration agencies.
+enum Record<'a> {
+    Crossref(&'a Item),
+    DataCite(&'a Item),
+}
+
+/// Returns the records of a Crossref or DataCite response, or `None` if the value is not DOI
+/// metadata. Both complete API responses and the works or lists of works contained in them are
+/// accepted.
+fn records(value: &Value) -> Option<Vec<Record<'_>>> {
+    // API responses wrap the work(s) in `message` (Crossref) or `data` (DataCite)
+    let object = value.as_object()?;
+    let value = object
+        .get("message")
+        .or_else(|| object.get("data"))
+        .unwrap_or(value);
+
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(object) => match object.get("items") {
+            Some(Value::Array(items)) => items,
+            _ => return record(value).map(|record| vec![record]),
+        },
+        _ => return None,
+    };
+    items.iter().map(record).collect()
+}
+
+fn record(value: &Value) -> Option<Record<'_>> {
+    let item = value.as_object()?;
+    if let Some(Value::Object(attributes)) = item.get("attributes") {
+        return Some(Record::DataCite(attributes));
+    }
+    if item.contains_key("doi") && (item.contains_key("titles") || item.contains_key("creators")) {
+        return Some(Record::DataCite(item));
+    }
+    // CSL-JSON items look similar, but always have an `id`
+    if item.contains_key("DOI") && !item.contains_key("id") {
+        return Some(Record::Crossref(item));
+    }
+    None
+}
+
+/// Whether the JSON value is Crossref or DataCite metadata rather than CSL-JSON.
+pub fn is_doi_metadata(value: &Value) -> bool {
+    match value {
+        // a top-level array could also be CSL-JSON, look at its items
+        Value::Array(items) => !items.is_empty() && items.iter().all(|item| record(item).is_some()),
+        _ => records(value).is_some(),
+    }
+}
+
+/// Converts Crossref or DataCite metadata into a library.
+///
+/// Entries are keyed by the key given for their DOI in `doi_keys`, or by their DOI itself,
+/// normalized to lowercase.
+pub fn from_doi_json(
+    value: &Value,
+    doi_keys: &HashMap<String, String>,
+) -> Result<Library, DecodeError> {
+    let records = match value {
+        Value::Array(items) => items.iter().map(record).collect(),
+        _ => records(value),
+    };
+    let Some(records) = records else {
+        return Err(DecodeError::new("expected Crossref or DataCite metadata"));
+    };
+
+    let entries = records
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let (doi, entry) = match record {
+                Record::Crossref(item) => (text(item, "DOI"), convert_crossref(item)),
+                Record::DataCite(item) => (text(item, "doi"), convert_datacite(item)),
+            };
+            let doi = doi
+                .map(|doi| normalize_doi(&doi))
+                .ok_or_else(|| DecodeError::new(format!("work {i} has no DOI")))?;
+            let key = doi_keys
+                .iter()
+                .find(|(other, _)| normalize_doi(other) == doi)
+                .map_or(doi, |(_, key)| key.clone());
+            Ok((key, entry))
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Normalizes a DOI for use as a key: DOIs are case-insensitive and often written as a URL.
+fn normalize_doi(doi: &str) -> String {
+    let doi = doi.trim();
+    let doi = [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "doi:",
+    ]
+    .into_iter()
+    .find_map(|prefix| doi.strip_prefix(prefix))
+    .unwrap_or(doi);
+    doi.to_lowercase()
+}
+
+/// Maps a Crossref work type to a Hayagriva entry type and, if applicable, the type of its
+/// container.
+fn crossref_types(kind: &str) -> (&'static str, Option<&'static str>) {
+    match kind {
+        "journal-article" => ("article", Some("periodical")),
+        "proceedings-article" => ("article", Some("proceedings")),
+        "posted-content" => ("article", None),
+        "book-chapter" | "book-section" | "book-part" => ("chapter", Some("book")),
+        "reference-entry" => ("entry", Some("reference")),
+        "book" | "monograph" | "reference-book" | "book-set" => ("book", None),
+        "edited-book" | "book-series" => ("anthology", None),
+        "proceedings" => ("proceedings", None),
+        "journal" => ("periodical", None),
+        "report" | "report-series" | "standard" => ("report", None),
+        "dissertation" => ("thesis", None),
+        "dataset" | "database" => ("repository", None),
+        _ => ("misc", None),
+    }
+}
+
+fn convert_crossref(item: &Item) -> EntryBuilder {
+    let kind = text(item, "type").unwrap_or_default();
+    let (entry_type, container_type) = crossref_types(&kind);
+    let mut entry = EntryBuilder::new(entry_type);
+
+    if let Some(title) = text(item, "title") {
+        let title = match text(item, "subtitle") {
+            Some(subtitle) => format!("{title}: {subtitle}"),
+            None => title,
+        };
+        entry.set_title(&title, text(item, "short-title").as_deref());
+    }
+    for person in crossref_names(item, "author") {
+        entry.push("author", person);
+    }
+    for person in crossref_names(item, "editor") {
+        entry.push("editor", person);
+    }
+    for person in crossref_names(item, "translator") {
+        entry.push_affiliated("translator", person);
+    }
+
+    // `issued` is the earliest of the print and online publication dates
+    if let Some(date) = ["issued", "published", "published-print", "published-online"]
+        .into_iter()
+        .find_map(|variable| csl::date(item, variable))
+    {
+        entry.set("date", date);
+    }
+    if let Some(url) = text(item, "URL") {
+        entry.set_url(&url, None);
+    }
+    if let Some(doi) = text(item, "DOI") {
+        entry.set_serial_number("doi", &doi);
+    }
+    if let Some(isbn) = text(item, "ISBN") {
+        entry.set_serial_number("isbn", &isbn);
+    }
+
+    if let Some(page) = text(item, "page") {
+        entry.set_text("page-range", &page);
+    }
+    if let Some(edition) = text(item, "edition-number") {
+        entry.set_text("edition", &edition);
+    }
+    if let Some(abstract_) = text(item, "abstract") {
+        entry.set_text("abstract", &strip_markup(&abstract_));
+    }
+    if let Some(language) = text(item, "language") {
+        entry.set_language(&language);
+    }
+    entry.set_publisher(
+        text(item, "publisher").as_deref(),
+        text(item, "publisher-location").as_deref(),
+    );
+
+    // volume, issue and ISSN describe the container if there is one, the entry itself otherwise
+    let container_title = text(item, "container-title");
+    let target = match (container_type, &container_title) {
+        (Some(container_type), Some(container_title)) => {
+            let parent = entry.parent(container_type);
+            let short = text(item, "short-container-title");
+            parent.set_title(
+                container_title,
+                short.as_deref().filter(|short| short != container_title),
+            );
+            parent
+        }
+        _ => &mut entry,
+    };
+    for field in ["volume", "issue"] {
+        if let Some(value) = text(item, field) {
+            target.set_text(field, &value);
+        }
+    }
+    if let Some(issn) = text(item, "ISSN") {
+        target.set_serial_number("issn", &issn);
+    }
+
+    entry
+}
+
+/// Maps a DataCite resource type to a Hayagriva entry type and, if applicable, the type of its
+/// container.
+fn datacite_types(kind: &str) -> (&'static str, Option<&'static str>) {
+    match kind {
+        "JournalArticle" | "DataPaper" => ("article", Some("periodical")),
+        "ConferencePaper" => ("article", Some("proceedings")),
+        "Preprint" => ("article", None),
+        "BookChapter" => ("chapter", Some("book")),
+        "Book" => ("book", None),
+        "Collection" => ("anthology", None),
+        "ConferenceProceeding" => ("proceedings", None),
+        "Journal" => ("periodical", None),
+        "Dissertation" => ("thesis", None),
+        "Report" | "Standard" => ("report", None),
+        "Dataset" | "Software" | "ComputationalNotebook" => ("repository", None),
+        "Audiovisual" => ("video", None),
+        "Sound" => ("audio", None),
+        "Image" => ("artwork", None),
+        _ => ("misc", None),
+    }
+}
+
+fn convert_datacite(item: &Item) -> EntryBuilder {
+    let kind = item
+        .get("types")
+        .and_then(Value::as_object)
+        .and_then(|types| text(types, "resourceTypeGeneral"))
+        .unwrap_or_default();
+    let (entry_type, container_type) = datacite_types(&kind);
+    let mut entry = EntryBuilder::new(entry_type);
+
+    // the main title is the one without a `titleType`
+    let titles = objects(item, "titles");
+    if let Some(title) = titles
+        .iter()
+        .find(|title| !title.contains_key("titleType"))
+        .or(titles.first())
+        .and_then(|title| text(title, "title"))
+    {
+        entry.set_text("title", &title);
+    }
+
+    for person in objects(item, "creators").into_iter().map(datacite_name) {
+        entry.push("author", person);
+    }
+    for contributor in objects(item, "contributors") {
+        if text(contributor, "contributorType").as_deref() == Some("Editor") {
+            entry.push("editor", datacite_name(contributor));
+        }
+    }
+
+    let issued = objects(item, "dates")
+        .into_iter()
+        .find(|date| text(date, "dateType").as_deref() == Some("Issued"))
+        .and_then(|date| text(date, "date"));
+    if let Some(date) = issued
+        .or_else(|| text(item, "publicationYear"))
+        .and_then(|date| builder::date_from_str(&date))
+    {
+        entry.set("date", date);
+    }
+    if let Some(url) = text(item, "url") {
+        entry.set_url(&url, None);
+    }
+    if let Some(doi) = text(item, "doi") {
+        entry.set_serial_number("doi", &doi);
+    }
+    if let Some(version) = text(item, "version") {
+        entry.set_serial_number("version", &version);
+    }
+
+    let abstract_ = objects(item, "descriptions")
+        .into_iter()
+        .find(|description| text(description, "descriptionType").as_deref() == Some("Abstract"))
+        .and_then(|description| text(description, "description"));
+    if let Some(abstract_) = abstract_ {
+        entry.set_text("abstract", &abstract_);
+    }
+    if let Some(language) = text(item, "language") {
+        entry.set_language(&language);
+    }
+    // newer API versions describe the publisher as an object
+    let publisher = match item.get("publisher") {
+        Some(Value::Object(publisher)) => text(publisher, "name"),
+        _ => text(item, "publisher"),
+    };
+    entry.set_publisher(publisher.as_deref(), None);
+
+    let container = item.get("container").and_then(Value::as_object);
+    if let Some(container) = container {
+        match (text(container, "firstPage"), text(container, "lastPage")) {
+            (Some(first), Some(last)) => entry.set_text("page-range", &format!("{first}-{last}")),
+            (Some(first), None) => entry.set_text("page-range", &first),
+            _ => {}
+        }
+
+        let container_title = text(container, "title");
+        let target = match (container_type, &container_title) {
+            (Some(container_type), Some(container_title)) => {
+                let parent = entry.parent(container_type);
+                parent.set_text("title", container_title);
+                parent
+            }
+            _ => &mut entry,
+        };
+        for field in ["volume", "issue"] {
+            if let Some(value) = text(container, field) {
+                target.set_text(field, &value);
+            }
+        }
+        if text(container, "identifierType").as_deref() == Some("ISSN") {
+            if let Some(issn) = text(container, "identifier") {
+                target.set_serial_number("issn", &issn);
+            }
+        }
+    }
+
+    entry
+}
+
+/// Reads a string or number, or the first element of an array of them. Crossref wraps most
+/// textual fields in arrays.
+fn text(item: &Item, key: &str) -> Option<String> {
+    let value = match item.get(key)? {
+        Value::Array(values) => values.first()?,
+        value => value,
+    };
+    match value {
+        Value::String(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+        Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
+/// Reads an array of objects.
+fn objects<'a>(item: &'a Item, key: &str) -> Vec<&'a Item> {
+    match item.get(key) {
+        Some(Value::Array(values)) => values.iter().filter_map(Value::as_object).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Reads Crossref contributors, which are either persons or organizations with only a `name`.
+fn crossref_names(item: &Item, key: &str) -> Vec<serde_yaml::Value> {
+    objects(item, key)
+        .into_iter()
+        .filter_map(|name| match text(name, "family") {
+            Some(family) => Some(builder::person(
+                &family,
+                text(name, "given").as_deref(),
+                None,
+                text(name, "suffix").as_deref(),
+            )),
+            None => text(name, "name").map(|name| builder::person(&name, None, None, None)),
+        })
+        .collect()
+}
+
+/// Reads a DataCite creator or contributor. Personal names are given either in parts or as
+/// `Family, Given`.
+fn datacite_name(name: &Item) -> serde_yaml::Value {
+    if let Some(family) = text(name, "familyName") {
+        return builder::person(&family, text(name, "givenName").as_deref(), None, None);
+    }
+    let full_name = text(name, "name").unwrap_or_default();
+    if text(name, "nameType").as_deref() == Some("Organizational") {
+        builder::person(&full_name, None, None, None)
+    } else {
+        builder::person_from_str(&full_name)
+    }
+}
+
+/// Removes the JATS tags that Crossref abstracts are marked up with.
+fn strip_markup(text: &str) -> String {
+    let mut result = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                result.push(' ');
+            }
+            c if !in_tag => result.push(c),
+            _ => {}
+        }
+    }
+    result.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossref() {
+        let data = r#"{
+            "status": "ok",
+            "message-type": "work",
+            "message": {
+                "DOI": "10.1000/NetWok",
+                "type": "journal-article",
+                "title": ["At-scale impact of the Net Wok"],
+                "author": [
+                    {"given": "Rick", "family": "Astley", "sequence": "first"},
+                    {"given": "Linda", "family": "Morris", "sequence": "additional"}
+                ],
+                "container-title": ["Armenian Journal of Proceedings"],
+                "volume": "61",
+                "page": "192-219",
+                "issued": {"date-parts": [[2020, 3, 4]]},
+                "abstract": "<jats:p>Dumplings.</jats:p>"
+            }
+        }"#;
+        let value: Value = serde_json::from_str(data).unwrap();
+        assert!(is_doi_metadata(&value));
+
+        let library = from_doi_json(&value, &HashMap::new()).unwrap();
+        let entry = library.get("10.1000/netwok").unwrap();
+        assert_eq!(entry.authors().unwrap().len(), 2);
+        assert_eq!(entry.doi(), Some("10.1000/NetWok"));
+        assert_eq!(entry.abstract_().unwrap().value.to_str(), "Dumplings.");
+        assert!(entry.parents()[0].volume().is_some());
+
+        let keys = HashMap::from([("10.1000/netwok".to_string(), "netwok".to_string())]);
+        let library = from_doi_json(&value, &keys).unwrap();
+        assert!(library.get("netwok").is_some());
+    }
+
+    #[test]
+    fn test_datacite() {
+        let data = r#"{
+            "data": [{
+                "id": "10.5281/zenodo.1234",
+                "type": "dois",
+                "attributes": {
+                    "doi": "10.5281/zenodo.1234",
+                    "titles": [{"title": "Net Wok"}],
+                    "creators": [
+                        {"name": "Astley, Rick", "nameType": "Personal"},
+                        {"name": "The Dumpling Collective", "nameType": "Organizational"}
+                    ],
+                    "publisher": "Zenodo",
+                    "publicationYear": 2021,
+                    "types": {"resourceTypeGeneral": "Software"},
+                    "version": "2.0.4"
+                }
+            }]
+        }"#;
+        let value: Value = serde_json::from_str(data).unwrap();
+        assert!(is_doi_metadata(&value));
+
+        let library = from_doi_json(&value, &HashMap::new()).unwrap();
+        let entry = library.get("10.5281/zenodo.1234").unwrap();
+        assert_eq!(entry.entry_type(), &hayagriva::types::EntryType::Repository);
+        assert_eq!(
+            entry.authors().unwrap()[0].given_name.as_deref(),
+            Some("Rick")
+        );
+        assert_eq!(entry.keyed_serial_number("version"), Some("2.0.4"));
+    }
+
+    #[test]
+    fn test_csl_json_is_not_doi_metadata() {
+        let value: Value =
+            serde_json::from_str(r#"[{"id": "netwok", "DOI": "10.1000/netwok"}]"#).unwrap();
+        assert!(!is_doi_metadata(&value));
+    }
+}
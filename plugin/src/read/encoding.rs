@@ -0,0 +1,170 @@
+//! Decoding of bibliography files into text. Besides UTF-8, older files are often encoded in
+//! UTF-16 (as written by some Windows tools) or in Latin-1 or its superset Windows-1252.
+
+use std::borrow::Cow;
+
+use crate::model::Encoding;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+/// The characters of Windows-1252 in the range `0x80..=0x9F`, where it differs from Latin-1. The
+/// five unassigned bytes are mapped to the corresponding control characters, like browsers do.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+/// Decodes the data in the given encoding, or in a detected one if none is given. Errors contain
+/// the byte offset at which decoding failed.
+///
+/// Without a declared encoding, a byte order mark determines the encoding. Otherwise, data that is
+/// valid UTF-8 is decoded as such, and anything else as Windows-1252, which accepts all bytes.
+pub fn decode(data: &[u8], encoding: Option<Encoding>) -> Result<Cow<'_, str>, String> {
+    let Some(encoding) = encoding else {
+        return detect(data);
+    };
+
+    match encoding {
+        Encoding::Utf8 => decode_utf8(data),
+        // without a byte order mark, assume little-endian like Windows does
+        Encoding::Utf16 => decode_utf16(data, data.starts_with(UTF16BE_BOM)),
+        Encoding::Utf16Le => decode_utf16(data, false),
+        Encoding::Utf16Be => decode_utf16(data, true),
+        Encoding::Latin1 => Ok(data.iter().map(|&b| char::from(b)).collect()),
+        Encoding::Windows1252 => Ok(decode_windows_1252(data)),
+    }
+}
+
+fn detect(data: &[u8]) -> Result<Cow<'_, str>, String> {
+    if data.starts_with(UTF8_BOM) {
+        return decode_utf8(data);
+    }
+    if data.starts_with(UTF16LE_BOM) {
+        return decode_utf16(data, false);
+    }
+    if data.starts_with(UTF16BE_BOM) {
+        return decode_utf16(data, true);
+    }
+    if let Some(big_endian) = utf16_without_bom(data) {
+        return decode_utf16(data, big_endian);
+    }
+
+    match std::str::from_utf8(data) {
+        Ok(text) => Ok(Cow::Borrowed(text)),
+        Err(_) => Ok(decode_windows_1252(data)),
+    }
+}
+
+/// Recognizes UTF-16 without a byte order mark by the zero bytes of ASCII characters, which never
+/// appear in text in the other encodings. Returns whether the data is big-endian.
+fn utf16_without_bom(data: &[u8]) -> Option<bool> {
+    let pairs = data.chunks_exact(2).take(100).collect::<Vec<_>>();
+    let big_endian = pairs
+        .iter()
+        .filter(|pair| pair[0] == 0 && pair[1] != 0)
+        .count();
+    let little_endian = pairs
+        .iter()
+        .filter(|pair| pair[0] != 0 && pair[1] == 0)
+        .count();
+
+    if pairs.is_empty() {
+        None
+    } else if big_endian * 2 >= pairs.len() {
+        Some(true)
+    } else if little_endian * 2 >= pairs.len() {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+fn decode_utf8(data: &[u8]) -> Result<Cow<'_, str>, String> {
+    let offset = if data.starts_with(UTF8_BOM) {
+        UTF8_BOM.len()
+    } else {
+        0
+    };
+    std::str::from_utf8(&data[offset..])
+        .map(Cow::Borrowed)
+        .map_err(|err| {
+            format!(
+                "invalid UTF-8 at byte offset {}",
+                offset + err.valid_up_to()
+            )
+        })
+}
+
+fn decode_utf16(data: &[u8], big_endian: bool) -> Result<Cow<'_, str>, String> {
+    let bom = if big_endian { UTF16BE_BOM } else { UTF16LE_BOM };
+    let offset = if data.starts_with(bom) { bom.len() } else { 0 };
+    let data = &data[offset..];
+    if !data.len().is_multiple_of(2) {
+        return Err(format!(
+            "truncated UTF-16 data at byte offset {}",
+            offset + data.len() - 1
+        ));
+    }
+
+    let units = data.chunks_exact(2).map(|pair| {
+        let pair = [pair[0], pair[1]];
+        if big_endian {
+            u16::from_be_bytes(pair)
+        } else {
+            u16::from_le_bytes(pair)
+        }
+    });
+
+    let mut text = String::with_capacity(data.len() / 2);
+    let mut position = offset;
+    for c in char::decode_utf16(units) {
+        let Ok(c) = c else {
+            return Err(format!(
+                "invalid UTF-16 (unpaired surrogate) at byte offset {position}"
+            ));
+        };
+        text.push(c);
+        position += 2 * c.len_utf16();
+    }
+    Ok(Cow::Owned(text))
+}
+
+fn decode_windows_1252(data: &[u8]) -> Cow<'_, str> {
+    data.iter()
+        .map(|&b| match b {
+            0x80..=0x9F => WINDOWS_1252[usize::from(b - 0x80)],
+            _ => char::from(b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        let utf16le = b"\xFF\xFEM\0\xFC\0l\0l\0e\0r\0";
+        let utf16be = b"\0M\0\xFC\0l\0l\0e\0r";
+        let cases: [(&[u8], Option<Encoding>); 7] = [
+            ("Müller".as_bytes(), None),
+            (b"\xEF\xBB\xBFM\xC3\xBCller", None),
+            (utf16le, None),
+            (utf16be, None),
+            (utf16be, Some(Encoding::Utf16Be)),
+            (b"M\xFCller", None),
+            (b"M\xFCller", Some(Encoding::Latin1)),
+        ];
+        for (data, encoding) in cases {
+            assert_eq!(decode(data, encoding).unwrap(), "Müller", "{data:?}");
+        }
+
+        assert_eq!(decode(b"\x93quoted\x94", None).unwrap(), "“quoted”");
+        assert_eq!(
+            decode(b"ab\xFCc", Some(Encoding::Utf8)).unwrap_err(),
+            "invalid UTF-8 at byte offset 2"
+        );
+    }
+}
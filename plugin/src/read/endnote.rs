@@ -0,0 +1,300 @@
+//! Decoding of EndNote XML, as exported via EndNote's "Export > XML" function.
+
+use hayagriva::Library;
+
+use super::builder::{self, EntryBuilder, KeyGenerator};
+use super::xml::Element;
+use super::DecodeError;
+
+/// Converts the records of an EndNote XML export into a library.
+///
+/// EndNote records don't have citation keys, so keys are generated by [`KeyGenerator`].
+pub fn from_endnote_xml(root: &Element) -> Result<Library, DecodeError> {
+    let records = match root.name.as_str() {
+        "xml" => root
+            .find(&["records"])
+            .map(|records| records.children("record").collect()),
+        "records" => Some(root.children("record").collect()),
+        "record" => Some(vec![root]),
+        _ => None,
+    };
+    let Some(records) = records else {
+        return Err(DecodeError::new(format!(
+            "expected EndNote records, found a `<{}>` element",
+            root.name
+        )));
+    };
+
+    let mut keys = KeyGenerator::default();
+    let entries = records
+        .into_iter()
+        .map(|record| {
+            let name = record
+                .find(&["contributors", "authors", "author"])
+                .and_then(Element::text)
+                .map(|author| author.split(',').next().unwrap_or_default().to_string())
+                .or_else(|| record.text_at(&["titles", "title"]));
+            let year = record.text_at(&["dates", "year"]);
+            let key = keys.generate(name.as_deref(), year.as_deref());
+            (key, convert_record(record))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Returns the name of a record's reference type, such as "Journal Article".
+fn ref_type(record: &Element) -> String {
+    let ref_type = record.child("ref-type");
+    if let Some(name) = ref_type.and_then(|ref_type| ref_type.attr("name")) {
+        return name.to_string();
+    }
+
+    // older exports only contain the numeric type
+    let name = match ref_type.and_then(Element::text).as_deref() {
+        Some("4") => "Bill",
+        Some("5") => "Book Section",
+        Some("6") => "Book",
+        Some("7") => "Case",
+        Some("10") => "Conference Proceedings",
+        Some("12") => "Web Page",
+        Some("17") => "Journal Article",
+        Some("21") => "Film or Broadcast",
+        Some("23") => "Newspaper Article",
+        Some("25") => "Patent",
+        Some("27") => "Report",
+        Some("28") => "Edited Book",
+        Some("31") => "Statute",
+        Some("32") => "Thesis",
+        Some("47") => "Conference Paper",
+        _ => "Generic",
+    };
+    name.to_string()
+}
+
+/// Maps an EndNote reference type to a Hayagriva entry type and, if applicable, the type of its
+/// container.
+fn entry_types(ref_type: &str) -> (&'static str, Option<&'static str>) {
+    match ref_type {
+        "Journal Article" | "Electronic Article" | "Magazine Article" => {
+            ("article", Some("periodical"))
+        }
+        "Newspaper Article" => ("article", Some("newspaper")),
+        "Conference Paper" | "Conference Proceedings" => ("article", Some("proceedings")),
+        "Book Section" | "Electronic Book Section" => ("chapter", Some("book")),
+        "Encyclopedia" | "Dictionary" => ("entry", Some("reference")),
+        "Book" | "Electronic Book" | "Classical Work" => ("book", None),
+        "Edited Book" => ("anthology", None),
+        "Thesis" => ("thesis", None),
+        "Report" | "Government Document" | "Standard" | "Grant" => ("report", None),
+        "Web Page" | "Blog" => ("web", None),
+        "Dataset" | "Computer Program" => ("repository", None),
+        "Patent" => ("patent", None),
+        // the reporter of a case and the code of a statute take the place of the container
+        "Case" => ("case", Some("periodical")),
+        "Statute" | "Bill" | "Legal Rule or Regulation" => ("legislation", Some("anthology")),
+        "Film or Broadcast" | "Audiovisual Material" => ("video", None),
+        "Music" => ("audio", None),
+        "Artwork" | "Figure" | "Map" | "Chart or Table" => ("artwork", None),
+        "Manuscript" | "Unpublished Work" => ("manuscript", None),
+        _ => ("misc", None),
+    }
+}
+
+/// How the persons of one of EndNote's contributor groups are represented in Hayagriva.
+enum Role {
+    Author,
+    Editor,
+    Affiliated(&'static str),
+}
+
+/// Maps EndNote's contributor groups to roles. Their meaning depends on the reference type, e.g.
+/// the secondary authors of a book section are the book's editors, but the secondary author of a
+/// thesis is the advisor.
+fn role(ref_type: &str, group: &str) -> Option<Role> {
+    match (ref_type, group) {
+        (_, "authors") => Some(Role::Author),
+        ("Film or Broadcast", "tertiary-authors") => Some(Role::Affiliated("producer")),
+        ("Film or Broadcast", "subsidiary-authors") => Some(Role::Affiliated("cast-member")),
+        (
+            "Thesis" | "Patent" | "Case" | "Statute" | "Bill" | "Legal Rule or Regulation",
+            "secondary-authors",
+        ) => None,
+        (_, "secondary-authors") => Some(Role::Editor),
+        (_, "subsidiary-authors") => Some(Role::Affiliated("translator")),
+        _ => None,
+    }
+}
+
+fn convert_record(record: &Element) -> EntryBuilder {
+    let ref_type = ref_type(record);
+    let (entry_type, container_type) = entry_types(&ref_type);
+    let mut entry = EntryBuilder::new(entry_type);
+
+    if let Some(title) = record.text_at(&["titles", "title"]) {
+        let short = record.text_at(&["titles", "short-title"]);
+        entry.set_title(&title, short.as_deref());
+    }
+
+    if let Some(contributors) = record.child("contributors") {
+        for group in contributors.elements() {
+            let Some(role) = role(&ref_type, &group.name) else {
+                continue;
+            };
+            for person in group.children("author").filter_map(Element::text) {
+                let person = builder::person_from_str(&person);
+                match role {
+                    Role::Author => entry.push("author", person),
+                    Role::Editor => entry.push("editor", person),
+                    Role::Affiliated(role) => entry.push_affiliated(role, person),
+                }
+            }
+        }
+    }
+
+    let year = record.text_at(&["dates", "year"]);
+    let pub_date = record.text_at(&["dates", "pub-dates", "date"]);
+    // the publication date is often only a month and day, e.g. "Mar 4"
+    let date = match (&year, &pub_date) {
+        (Some(year), Some(pub_date)) => builder::date_from_str(&format!("{year} {pub_date}"))
+            .or_else(|| builder::date_from_str(year)),
+        (Some(year), None) => builder::date_from_str(year),
+        (None, Some(pub_date)) => builder::date_from_str(pub_date),
+        (None, None) => None,
+    };
+    if let Some(date) = date {
+        entry.set("date", date);
+    }
+
+    let url = record
+        .find(&["urls", "related-urls", "url"])
+        .or_else(|| record.find(&["urls", "web-urls", "url"]))
+        .and_then(Element::text);
+    if let Some(url) = url {
+        let accessed = record
+            .text_at(&["access-date"])
+            .and_then(|date| builder::date_from_str(&date));
+        entry.set_url(&url, accessed);
+    }
+
+    if let Some(doi) = record.text_at(&["electronic-resource-num"]) {
+        entry.set_serial_number("doi", &doi);
+    }
+    if let Some(number) = record.text_at(&["isbn"]) {
+        // the same field is used for ISBNs and ISSNs, tell them apart by their length
+        let digits = number.chars().filter(char::is_ascii_alphanumeric).count();
+        let kind = if digits == 8 { "issn" } else { "isbn" };
+        entry.set_serial_number(kind, &number);
+    }
+    // the accession number of records imported from PubMed is the PMID
+    let database = record
+        .text_at(&["remote-database-name"])
+        .unwrap_or_default();
+    if database.contains("PubMed") || database.contains("MEDLINE") {
+        if let Some(pmid) = record.text_at(&["accession-num"]) {
+            entry.set_serial_number("pmid", &pmid);
+        }
+    }
+
+    for (field, element) in [
+        ("page-range", "pages"),
+        ("edition", "edition"),
+        ("abstract", "abstract"),
+        ("note", "notes"),
+        ("genre", "work-type"),
+        ("call-number", "call-num"),
+    ] {
+        if let Some(value) = record.text_at(&[element]) {
+            entry.set_text(field, &value);
+        }
+    }
+    if let Some(language) = record.text_at(&["language"]) {
+        entry.set_language(&language);
+    }
+
+    entry.set_publisher(
+        record.text_at(&["publisher"]).as_deref(),
+        record.text_at(&["pub-location"]).as_deref(),
+    );
+
+    // volume and number describe the container if there is one, the entry itself otherwise
+    let container_title = record
+        .text_at(&["titles", "secondary-title"])
+        .or_else(|| record.text_at(&["periodical", "full-title"]));
+    let target = match (container_type, &container_title) {
+        (Some(container_type), Some(container_title)) => {
+            let parent = entry.parent(container_type);
+            parent.set_text("title", container_title);
+            parent
+        }
+        _ => &mut entry,
+    };
+    if let Some(volume) = record.text_at(&["volume"]) {
+        target.set_text("volume", &volume);
+    }
+    if let Some(number) = record.text_at(&["number"]) {
+        match entry_type {
+            "report" | "patent" => target.set_serial_number("serial", &number),
+            _ => target.set_text("issue", &number),
+        }
+    }
+
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::xml;
+
+    #[test]
+    fn test_records() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8" ?>
+            <xml><records>
+                <record>
+                    <ref-type name="Journal Article">17</ref-type>
+                    <contributors><authors>
+                        <author><style face="normal" font="default" size="100%">Astley, Rick</style></author>
+                        <author>Morris, Linda</author>
+                    </authors></contributors>
+                    <titles>
+                        <title>At-scale impact of the Net Wok</title>
+                        <secondary-title>Armenian Journal of Proceedings</secondary-title>
+                    </titles>
+                    <pages>192-219</pages>
+                    <volume>61</volume>
+                    <dates><year>2020</year><pub-dates><date>Mar 4</date></pub-dates></dates>
+                    <electronic-resource-num>10.1000/netwok</electronic-resource-num>
+                    <remote-database-name>PubMed</remote-database-name>
+                    <accession-num>12345678</accession-num>
+                </record>
+                <record>
+                    <ref-type name="Book Section">5</ref-type>
+                    <contributors>
+                        <authors><author>Astley, Rick</author></authors>
+                        <secondary-authors><author>Morris, Linda</author></secondary-authors>
+                    </contributors>
+                    <titles>
+                        <title>Dumplings &amp; more</title>
+                        <secondary-title>The Wok Book</secondary-title>
+                    </titles>
+                    <dates><year>2020</year></dates>
+                </record>
+            </records></xml>"#;
+        let library = from_endnote_xml(&xml::parse(data).unwrap()).unwrap();
+
+        let keys = library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["astley2020", "astley2020a"]);
+
+        let article = library.get("astley2020").unwrap();
+        assert_eq!(article.authors().unwrap().len(), 2);
+        assert_eq!(article.date().unwrap().day, Some(3));
+        assert_eq!(article.pmid(), Some("12345678"));
+        assert!(article.parents()[0].volume().is_some());
+
+        let chapter = library.get("astley2020a").unwrap();
+        assert_eq!(chapter.entry_type(), &hayagriva::types::EntryType::Chapter);
+        assert_eq!(chapter.editors().unwrap().len(), 1);
+        assert_eq!(chapter.title().unwrap().value.to_str(), "Dumplings & more");
+    }
+}
@@ -0,0 +1,299 @@
+//! Decoding of JATS (Journal Article Tag Suite) reference lists, as produced by journal production
+//! pipelines. See https://jats.nlm.nih.gov/publishing/tag-library/1.3/element/ref-list.html
+
+use hayagriva::Library;
+
+use super::builder::{self, EntryBuilder};
+use super::xml::Element;
+use super::DecodeError;
+
+/// The elements that contain the actual citation inside a `<ref>`.
+const CITATIONS: &[&str] = &[
+    "element-citation",
+    "mixed-citation",
+    "nlm-citation",
+    "citation",
+];
+
+/// Converts the references of all `<ref-list>`s in a JATS document (or a bare `<ref-list>`) into
+/// a library. References are keyed by their `id` attribute.
+pub fn from_jats(root: &Element) -> Result<Library, DecodeError> {
+    let refs = root
+        .descendants("ref-list")
+        .into_iter()
+        .flat_map(|list| list.children("ref"))
+        .collect::<Vec<_>>();
+    if refs.is_empty() {
+        return Err(DecodeError::new("no `<ref>` elements found"));
+    }
+
+    let entries = refs
+        .into_iter()
+        .enumerate()
+        .map(|(i, reference)| {
+            let key = reference
+                .attr("id")
+                .ok_or_else(|| DecodeError::new(format!("reference {i} has no `id`")))?;
+            let citation = reference
+                .elements()
+                .find(|element| CITATIONS.contains(&element.name.as_str()))
+                .ok_or_else(|| {
+                    DecodeError::new(format!("reference `{key}` contains no citation"))
+                })?;
+            Ok((key.to_string(), convert_citation(citation)))
+        })
+        .collect::<Result<Vec<_>, DecodeError>>()?;
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Maps a JATS publication type to a Hayagriva entry type and, if applicable, the type of its
+/// container, which is described by the `<source>`.
+fn entry_types(kind: &str, has_part_title: bool) -> (&'static str, Option<&'static str>) {
+    match kind {
+        "journal" | "magazine" => ("article", Some("periodical")),
+        "newspaper" => ("article", Some("newspaper")),
+        "confproc" | "conference" => ("article", Some("proceedings")),
+        "book" if has_part_title => ("chapter", Some("book")),
+        "book" => ("book", None),
+        "thesis" => ("thesis", None),
+        "report" | "gov" | "standard" => ("report", None),
+        "patent" => ("patent", None),
+        "web" | "webpage" | "website" => ("web", None),
+        "data" | "dataset" | "software" => ("repository", None),
+        "preprint" => ("article", None),
+        _ => ("misc", None),
+    }
+}
+
+fn convert_citation(citation: &Element) -> EntryBuilder {
+    let kind = citation
+        .attr("publication-type")
+        .or_else(|| citation.attr("citation-type"))
+        .unwrap_or_default();
+    let part_title = ["article-title", "chapter-title", "part-title", "data-title"]
+        .into_iter()
+        .find_map(|name| citation.text_at(&[name]));
+    let source = citation.text_at(&["source"]);
+
+    let (entry_type, container_type) = entry_types(kind, part_title.is_some());
+    let mut entry = EntryBuilder::new(entry_type);
+
+    // without a container, the source is the title of the work itself
+    let (title, container_title) = match (part_title, container_type) {
+        (Some(title), Some(_)) => (Some(title), source),
+        (Some(title), None) => (Some(title), None),
+        (None, _) => (source, None),
+    };
+    if let Some(title) = title {
+        entry.set_text("title", &title);
+    }
+
+    let groups = citation.children("person-group").collect::<Vec<_>>();
+    if groups.is_empty() {
+        // names directly inside the citation are authors
+        for person in persons(citation) {
+            entry.push("author", person);
+        }
+    }
+    for group in groups {
+        for person in persons(group) {
+            match group.attr("person-group-type").unwrap_or("author") {
+                "author" | "inventor" => entry.push("author", person),
+                "editor" => entry.push("editor", person),
+                "translator" => entry.push_affiliated("translator", person),
+                "compiler" => entry.push_affiliated("compiler", person),
+                "director" => entry.push_affiliated("director", person),
+                _ => {}
+            }
+        }
+    }
+
+    if let Some(year) = citation.text_at(&["year"]) {
+        let date = [
+            Some(year),
+            citation.text_at(&["month"]),
+            citation.text_at(&["day"]),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+        if let Some(date) = builder::date_from_str(&date) {
+            entry.set("date", date);
+        }
+    }
+
+    let url = citation
+        .child("ext-link")
+        .and_then(|link| {
+            link.attr("href")
+                .map(str::to_string)
+                .or_else(|| link.text())
+        })
+        .or_else(|| citation.text_at(&["uri"]));
+    if let Some(url) = url {
+        let accessed = citation
+            .children("date-in-citation")
+            .find(|date| date.attr("content-type") == Some("access-date"))
+            .and_then(|date| {
+                date.attr("iso-8601-date")
+                    .map(str::to_string)
+                    .or_else(|| date.text())
+            })
+            .and_then(|date| builder::date_from_str(&date));
+        entry.set_url(&url, accessed);
+    }
+
+    for pub_id in citation.children("pub-id") {
+        let kind = match pub_id.attr("pub-id-type") {
+            Some("doi") => "doi",
+            Some("pmid") => "pmid",
+            Some("pmcid") => "pmcid",
+            Some("arxiv") => "arxiv",
+            _ => continue,
+        };
+        if let Some(value) = pub_id.text() {
+            entry.set_serial_number(kind, &value);
+        }
+    }
+    if let Some(isbn) = citation.text_at(&["isbn"]) {
+        entry.set_serial_number("isbn", &isbn);
+    }
+
+    match (citation.text_at(&["fpage"]), citation.text_at(&["lpage"])) {
+        (Some(first), Some(last)) => entry.set_text("page-range", &format!("{first}-{last}")),
+        (Some(first), None) => entry.set_text("page-range", &first),
+        _ => {
+            if let Some(elocation) = citation.text_at(&["elocation-id"]) {
+                entry.set_text("page-range", &elocation);
+            }
+        }
+    }
+    if let Some(edition) = citation.text_at(&["edition"]) {
+        entry.set_text("edition", &edition);
+    }
+    if let Some(version) = citation.text_at(&["version"]) {
+        entry.set_serial_number("version", &version);
+    }
+    if let Some(comment) = citation.text_at(&["comment"]) {
+        entry.set_text("note", &comment);
+    }
+    entry.set_publisher(
+        citation.text_at(&["publisher-name"]).as_deref(),
+        citation.text_at(&["publisher-loc"]).as_deref(),
+    );
+
+    // volume, issue and ISSN describe the container if there is one, the entry itself otherwise
+    let target = match (container_type, &container_title) {
+        (Some(container_type), Some(container_title)) => {
+            let parent = entry.parent(container_type);
+            parent.set_text("title", container_title);
+            parent
+        }
+        _ => &mut entry,
+    };
+    for field in ["volume", "issue"] {
+        if let Some(value) = citation.text_at(&[field]) {
+            target.set_text(field, &value);
+        }
+    }
+    if let Some(issn) = citation.text_at(&["issn"]) {
+        target.set_serial_number("issn", &issn);
+    }
+
+    if let Some(conference) = citation.text_at(&["conf-name"]) {
+        let parent = match entry.existing_parent() {
+            Some(parent) => parent.parent("conference"),
+            None => entry.parent("conference"),
+        };
+        parent.set_text("title", &conference);
+        if let Some(location) = citation.text_at(&["conf-loc"]) {
+            parent.set_text("location", &location);
+        }
+    }
+
+    entry
+}
+
+/// Reads the `<name>`, `<string-name>` and `<collab>` elements of a person group.
+fn persons(group: &Element) -> Vec<serde_yaml::Value> {
+    group
+        .elements()
+        .filter_map(|element| match element.name.as_str() {
+            "name" | "string-name" => match element.text_at(&["surname"]) {
+                Some(surname) => Some(builder::person(
+                    &surname,
+                    element.text_at(&["given-names"]).as_deref(),
+                    element.text_at(&["prefix"]).as_deref(),
+                    element.text_at(&["suffix"]).as_deref(),
+                )),
+                // unstructured names, e.g. `<string-name>Astley, R.</string-name>`
+                None => element.text().map(|name| builder::person_from_str(&name)),
+            },
+            "collab" => element
+                .text()
+                .map(|name| builder::person(&name, None, None, None)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::xml;
+
+    #[test]
+    fn test_ref_list() {
+        let data = r#"<ref-list>
+            <title>References</title>
+            <ref id="B1">
+                <label>1</label>
+                <element-citation publication-type="journal">
+                    <person-group person-group-type="author">
+                        <name><surname>Astley</surname><given-names>R</given-names></name>
+                        <name><surname>Morris</surname><given-names>L</given-names></name>
+                    </person-group>
+                    <article-title>At-scale impact of the Net Wok</article-title>
+                    <source>Armenian Journal of Proceedings</source>
+                    <year>2020</year>
+                    <volume>61</volume>
+                    <fpage>192</fpage>
+                    <lpage>219</lpage>
+                    <pub-id pub-id-type="doi">10.1000/netwok</pub-id>
+                </element-citation>
+            </ref>
+            <ref id="B2">
+                <mixed-citation publication-type="book">
+                    <person-group person-group-type="author">
+                        <string-name><surname>Morris</surname>, <given-names>L</given-names></string-name>
+                    </person-group>. <source>The Wok Book</source>.
+                    <publisher-loc>Yerevan</publisher-loc>: <publisher-name>Dumpling Press</publisher-name>;
+                    <year>2019</year>.
+                </mixed-citation>
+            </ref>
+        </ref-list>"#;
+        let library = from_jats(&xml::parse(data).unwrap()).unwrap();
+
+        let keys = library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["B1", "B2"]);
+
+        let article = library.get("B1").unwrap();
+        assert_eq!(article.authors().unwrap().len(), 2);
+        assert_eq!(article.doi(), Some("10.1000/netwok"));
+        assert!(article.parents()[0].volume().is_some());
+
+        let book = library.get("B2").unwrap();
+        assert_eq!(book.entry_type(), &hayagriva::types::EntryType::Book);
+        assert_eq!(book.title().unwrap().value.to_str(), "The Wok Book");
+        assert!(book.publisher().is_some());
+    }
+
+    #[test]
+    fn test_missing_id() {
+        let data = "<ref-list><ref><element-citation/></ref></ref-list>";
+        let err = from_jats(&xml::parse(data).unwrap()).unwrap_err();
+        assert_eq!(err.message, "reference 0 has no `id`");
+    }
+}
@@ -0,0 +1,444 @@
+//! Decoding of MODS (Metadata Object Description Schema), the XML format used by library
+//! systems. See https://www.loc.gov/standards/mods/
+
+use hayagriva::Library;
+
+use super::builder::{self, EntryBuilder, KeyGenerator};
+use super::xml::Element;
+use super::DecodeError;
+
+/// Converts a `<modsCollection>` or a single `<mods>` record into a library.
+///
+/// Records are keyed by their `ID` attribute or, failing that, their `<recordIdentifier>`.
+/// Records without either get a key generated by [`KeyGenerator`].
+pub fn from_mods(root: &Element) -> Result<Library, DecodeError> {
+    let records = match root.name.as_str() {
+        "modsCollection" => root.children("mods").collect(),
+        "mods" => vec![root],
+        _ => {
+            return Err(DecodeError::new(format!(
+                "expected MODS records, found a `<{}>` element",
+                root.name
+            )))
+        }
+    };
+
+    let explicit_key = |record: &Element| {
+        record
+            .attr("ID")
+            .map(str::to_string)
+            .or_else(|| record.text_at(&["recordInfo", "recordIdentifier"]))
+    };
+    let reserved = records
+        .iter()
+        .filter_map(|record| explicit_key(record))
+        .collect::<Vec<_>>();
+    let mut keys = KeyGenerator::new(reserved.iter().map(String::as_str));
+
+    let entries = records
+        .into_iter()
+        .map(|record| {
+            let key = explicit_key(record).unwrap_or_else(|| {
+                let name = names(record)
+                    .into_iter()
+                    .find(|(role, _)| *role == Role::Author)
+                    .map(|(_, name)| name.family)
+                    .or_else(|| title(record).map(|(title, _)| title));
+                keys.generate(name.as_deref(), date(record).as_deref())
+            });
+            (key, convert_record(record))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Returns the genres of a record or related item, in lowercase.
+fn genres(element: &Element) -> Vec<String> {
+    element
+        .children("genre")
+        .filter_map(Element::text)
+        .map(|genre| genre.to_lowercase())
+        .collect()
+}
+
+/// Maps the genre and resource type of a record without a host item to a Hayagriva entry type.
+fn standalone_type(record: &Element) -> &'static str {
+    for genre in genres(record) {
+        let entry_type = match genre.as_str() {
+            "book" => "book",
+            "thesis" => "thesis",
+            "conference publication" => "proceedings",
+            "report" | "technical report" => "report",
+            "web site" | "web page" | "webpage" => "web",
+            "computer program" | "software" | "database" | "dataset" => "repository",
+            "patent" => "patent",
+            "motion picture" | "videorecording" | "video" => "video",
+            "periodical" | "academic journal" | "journal" => "periodical",
+            "manuscript" => "manuscript",
+            _ => continue,
+        };
+        return entry_type;
+    }
+
+    match record.text_at(&["typeOfResource"]).as_deref() {
+        Some("software, multimedia") => "repository",
+        Some("moving image") => "video",
+        Some("sound recording" | "sound recording-musical" | "sound recording-nonmusical") => {
+            "audio"
+        }
+        Some("still image" | "cartographic") => "artwork",
+        Some("text") if record.find(&["originInfo", "publisher"]).is_some() => "book",
+        _ => "misc",
+    }
+}
+
+/// Maps the genre of a record's host item to the entry type of the record and its container.
+fn hosted_types(host: &Element) -> (&'static str, &'static str) {
+    let issuance = host.text_at(&["originInfo", "issuance"]);
+    for genre in genres(host) {
+        let types = match genre.as_str() {
+            "newspaper" => ("article", "newspaper"),
+            "periodical" | "academic journal" | "journal" | "magazine" => ("article", "periodical"),
+            "conference publication" => ("article", "proceedings"),
+            "book" => ("chapter", "book"),
+            "web site" => ("web", "web"),
+            _ => continue,
+        };
+        return types;
+    }
+
+    match issuance.as_deref() {
+        Some("continuing" | "serial") => ("article", "periodical"),
+        _ => ("chapter", "book"),
+    }
+}
+
+/// The main title and, if present, the abbreviated title.
+fn title(element: &Element) -> Option<(String, Option<String>)> {
+    let main = element
+        .children("titleInfo")
+        .find(|info| info.attr("type").is_none())
+        .or_else(|| element.child("titleInfo"))?;
+    let title = main.text_at(&["title"])?;
+    let title = match main.text_at(&["nonSort"]) {
+        Some(non_sort) => format!("{non_sort} {title}"),
+        None => title,
+    };
+    let title = match main.text_at(&["subTitle"]) {
+        Some(subtitle) => format!("{title}: {subtitle}"),
+        None => title,
+    };
+
+    let short = element
+        .children("titleInfo")
+        .find(|info| info.attr("type") == Some("abbreviated"))
+        .and_then(|info| info.text_at(&["title"]));
+    Some((title, short))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Role {
+    Author,
+    Editor,
+    Affiliated(&'static str),
+}
+
+/// Maps a MARC relator term or code to a role.
+fn role(term: &str) -> Option<Role> {
+    let role = match term.to_lowercase().as_str() {
+        "author" | "aut" | "creator" | "cre" | "inventor" | "inv" => Role::Author,
+        "editor" | "edt" => Role::Editor,
+        "translator" | "trl" => Role::Affiliated("translator"),
+        "compiler" | "com" => Role::Affiliated("compiler"),
+        "composer" | "cmp" => Role::Affiliated("composer"),
+        "director" | "drt" => Role::Affiliated("director"),
+        "illustrator" | "ill" => Role::Affiliated("illustrator"),
+        "narrator" | "nrt" => Role::Affiliated("narrator"),
+        "producer" | "pro" => Role::Affiliated("producer"),
+        "organizer" | "orm" => Role::Affiliated("organizer"),
+        "annotator" | "ann" => Role::Affiliated("annotator"),
+        "commentator" | "cmm" => Role::Affiliated("commentator"),
+        _ => return None,
+    };
+    Some(role)
+}
+
+struct Name {
+    family: String,
+    value: serde_yaml::Value,
+}
+
+/// Returns the names of a record or related item with their roles. Names without a role are
+/// authors.
+fn names(element: &Element) -> Vec<(Role, Name)> {
+    element
+        .children("name")
+        .filter_map(|name| {
+            let role = match name.find(&["role", "roleTerm"]).and_then(Element::text) {
+                Some(term) => role(&term)?,
+                None => Role::Author,
+            };
+
+            let part = |kind: &str| {
+                name.children("namePart")
+                    .find(|part| part.attr("type") == Some(kind))
+                    .and_then(Element::text)
+            };
+            let name = match (part("family"), name.attr("type")) {
+                (Some(family), _) => Name {
+                    value: builder::person(&family, part("given").as_deref(), None, None),
+                    family,
+                },
+                (None, Some("corporate" | "conference")) => {
+                    let family = name
+                        .children("namePart")
+                        .filter_map(Element::text)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    Name {
+                        value: builder::person(&family, None, None, None),
+                        family,
+                    }
+                }
+                // a single name part of the form `Family, Given`
+                _ => {
+                    let full_name = name.text_at(&["namePart"])?;
+                    Name {
+                        family: full_name.split(',').next().unwrap_or_default().to_string(),
+                        value: builder::person_from_str(&full_name),
+                    }
+                }
+            };
+            Some((role, name))
+        })
+        .collect()
+}
+
+/// Returns the issue date of a record, which is also found in the `<part>` of its host.
+fn date(record: &Element) -> Option<String> {
+    record
+        .text_at(&["originInfo", "dateIssued"])
+        .or_else(|| record.text_at(&["part", "date"]))
+        .or_else(|| {
+            record
+                .children("relatedItem")
+                .find(|item| item.attr("type") == Some("host"))
+                .and_then(|host| {
+                    host.text_at(&["part", "date"])
+                        .or_else(|| host.text_at(&["originInfo", "dateIssued"]))
+                })
+        })
+        .or_else(|| record.text_at(&["originInfo", "dateCreated"]))
+}
+
+fn identifier(element: &Element, kind: &str) -> Option<String> {
+    element
+        .children("identifier")
+        .find(|identifier| identifier.attr("type") == Some(kind))
+        .and_then(Element::text)
+}
+
+fn convert_record(record: &Element) -> EntryBuilder {
+    let host = record
+        .children("relatedItem")
+        .find(|item| item.attr("type") == Some("host"));
+    let (entry_type, container_type) = match host {
+        Some(host) => {
+            let (entry_type, container_type) = hosted_types(host);
+            (entry_type, Some(container_type))
+        }
+        None => (standalone_type(record), None),
+    };
+    let mut entry = EntryBuilder::new(entry_type);
+
+    if let Some((title, short)) = title(record) {
+        entry.set_title(&title, short.as_deref());
+    }
+    push_names(&mut entry, record);
+
+    if let Some(date) = date(record).and_then(|date| builder::date_from_str(&date)) {
+        entry.set("date", date);
+    }
+    let location = record.find(&["location", "url"]);
+    if let Some(url) = location
+        .and_then(Element::text)
+        .or_else(|| identifier(record, "uri"))
+    {
+        let accessed = location
+            .and_then(|location| location.attr("dateLastAccessed"))
+            .and_then(builder::date_from_str);
+        entry.set_url(&url, accessed);
+    }
+
+    for kind in ["doi", "isbn", "issn"] {
+        if let Some(value) = identifier(record, kind) {
+            entry.set_serial_number(kind, &value);
+        }
+    }
+
+    for (field, path) in [
+        ("edition", &["originInfo", "edition"][..]),
+        ("abstract", &["abstract"]),
+        ("note", &["note"]),
+        ("call-number", &["classification"]),
+    ] {
+        if let Some(value) = record.text_at(path) {
+            entry.set_text(field, &value);
+        }
+    }
+    if let Some(language) = record.text_at(&["language", "languageTerm"]) {
+        entry.set_language(language_code(&language));
+    }
+    entry.set_publisher(
+        record.text_at(&["originInfo", "publisher"]).as_deref(),
+        record
+            .text_at(&["originInfo", "place", "placeTerm"])
+            .as_deref(),
+    );
+
+    if let (Some(host), Some(container_type)) = (host, container_type) {
+        let parent = entry.parent(container_type);
+        if let Some((title, short)) = title(host) {
+            parent.set_title(&title, short.as_deref());
+        }
+        push_names(parent, host);
+        parent.set_publisher(
+            host.text_at(&["originInfo", "publisher"]).as_deref(),
+            host.text_at(&["originInfo", "place", "placeTerm"])
+                .as_deref(),
+        );
+        for kind in ["isbn", "issn"] {
+            if let Some(value) = identifier(host, kind) {
+                parent.set_serial_number(kind, &value);
+            }
+        }
+    }
+
+    // volume, issue and pages are given in the `<part>` of the record or its host
+    let part = record
+        .child("part")
+        .or_else(|| host.and_then(|host| host.child("part")));
+    if let Some(part) = part {
+        set_part(&mut entry, part);
+    }
+
+    entry
+}
+
+fn push_names(entry: &mut EntryBuilder, element: &Element) {
+    for (role, name) in names(element) {
+        match role {
+            Role::Author => entry.push("author", name.value),
+            Role::Editor => entry.push("editor", name.value),
+            Role::Affiliated(role) => entry.push_affiliated(role, name.value),
+        }
+    }
+}
+
+/// Sets the volume and issue of the entry's container and the entry's page range.
+fn set_part(entry: &mut EntryBuilder, part: &Element) {
+    let detail = |kind: &str| {
+        part.children("detail")
+            .find(|detail| detail.attr("type") == Some(kind))
+            .and_then(|detail| detail.text_at(&["number"]))
+    };
+    let volume = detail("volume");
+    let issue = detail("issue").or_else(|| detail("number"));
+
+    let target = match entry.existing_parent() {
+        Some(parent) => parent,
+        None => entry,
+    };
+    if let Some(volume) = volume {
+        target.set_text("volume", &volume);
+    }
+    if let Some(issue) = issue {
+        target.set_text("issue", &issue);
+    }
+
+    let extent = part.child("extent");
+    let pages = match (
+        extent.and_then(|extent| extent.text_at(&["start"])),
+        extent.and_then(|extent| extent.text_at(&["end"])),
+    ) {
+        (Some(start), Some(end)) => Some(format!("{start}-{end}")),
+        (Some(start), None) => Some(start),
+        _ => extent.and_then(|extent| extent.text_at(&["list"])),
+    };
+    if let Some(pages) = pages {
+        entry.set_text("page-range", &pages);
+    }
+}
+
+/// Maps the ISO 639-2 codes used by MODS for common languages to the corresponding two-letter
+/// codes.
+fn language_code(code: &str) -> &str {
+    match code {
+        "eng" => "en",
+        "ger" | "deu" => "de",
+        "fre" | "fra" => "fr",
+        "spa" => "es",
+        "ita" => "it",
+        "por" => "pt",
+        "dut" | "nld" => "nl",
+        "rus" => "ru",
+        "jpn" => "ja",
+        "chi" | "zho" => "zh",
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read::xml;
+
+    #[test]
+    fn test_records() {
+        let data = r#"<?xml version="1.0"?>
+            <modsCollection xmlns="http://www.loc.gov/mods/v3">
+                <mods ID="netwok">
+                    <titleInfo><title>At-scale impact of the Net Wok</title></titleInfo>
+                    <name type="personal">
+                        <namePart type="family">Astley</namePart>
+                        <namePart type="given">Rick</namePart>
+                        <role><roleTerm type="text">author</roleTerm></role>
+                    </name>
+                    <relatedItem type="host">
+                        <titleInfo><title>Armenian Journal of Proceedings</title></titleInfo>
+                        <originInfo><issuance>continuing</issuance></originInfo>
+                        <part>
+                            <detail type="volume"><number>61</number></detail>
+                            <extent unit="pages"><start>192</start><end>219</end></extent>
+                            <date>2020</date>
+                        </part>
+                    </relatedItem>
+                    <identifier type="doi">10.1000/netwok</identifier>
+                </mods>
+                <mods>
+                    <titleInfo><nonSort>The</nonSort><title>Wok Book</title></titleInfo>
+                    <name type="personal"><namePart>Morris, Linda</namePart></name>
+                    <originInfo>
+                        <publisher>Dumpling Press</publisher>
+                        <dateIssued>2019</dateIssued>
+                    </originInfo>
+                    <genre authority="marcgt">book</genre>
+                </mods>
+            </modsCollection>"#;
+        let library = from_mods(&xml::parse(data).unwrap()).unwrap();
+
+        let keys = library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["netwok", "morris2019"]);
+
+        let article = library.get("netwok").unwrap();
+        assert_eq!(article.entry_type(), &hayagriva::types::EntryType::Article);
+        assert_eq!(article.date().unwrap().year, 2020);
+        assert!(article.page_range().is_some());
+        assert!(article.parents()[0].volume().is_some());
+
+        let book = library.get("morris2019").unwrap();
+        assert_eq!(book.entry_type(), &hayagriva::types::EntryType::Book);
+        assert_eq!(book.title().unwrap().value.to_str(), "The Wok Book");
+    }
+}
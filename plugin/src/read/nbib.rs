@@ -0,0 +1,313 @@
+//! Decoding of the MEDLINE/PubMed format (`.nbib`), as exported by PubMed's "Cite" and "Save"
+//! functions. See https://www.nlm.nih.gov/bsd/mms/medlineelements.html
+
+use hayagriva::Library;
+
+use super::builder::{self, EntryBuilder, KeyGenerator};
+use super::DecodeError;
+
+/// A single record, starting with a `PMID` tag.
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    fields: Vec<(String, String)>,
+}
+
+impl Record {
+    fn get(&self, tag: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, value)| t == tag && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn all<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a str> {
+        self.fields
+            .iter()
+            .filter(move |(t, value)| t == tag && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the identifier of the given kind from the `LID` and `AID` tags, whose values look
+    /// like `10.1000/xyz [doi]`.
+    fn article_id(&self, kind: &str) -> Option<&str> {
+        let suffix = format!("[{kind}]");
+        self.all("LID")
+            .chain(self.all("AID"))
+            .find_map(|value| value.strip_suffix(&suffix))
+            .map(str::trim)
+    }
+}
+
+/// Parses MEDLINE records into a library.
+///
+/// MEDLINE records don't have citation keys, so keys are generated by [`KeyGenerator`]. The PMID
+/// and, if present, PMCID and DOI are kept as serial numbers.
+pub fn from_nbib_str(data: &str) -> Result<Library, DecodeError> {
+    let records = parse(data)?;
+
+    let mut keys = KeyGenerator::default();
+    let entries = records
+        .iter()
+        .map(|record| {
+            let name = record
+                .get("FAU")
+                .or_else(|| record.get("AU"))
+                .and_then(|author| author.split([',', ' ']).next())
+                .or_else(|| record.get("TI"));
+            let key = keys.generate(name, record.get("DP"));
+            (key, convert_record(record))
+        })
+        .collect::<Vec<_>>();
+
+    Ok(builder::into_library(entries)?)
+}
+
+/// Splits the data into records, reporting structural errors with their location.
+fn parse(data: &str) -> Result<Vec<Record>, DecodeError> {
+    let mut records: Vec<Record> = Vec::new();
+    // whether the previous line was blank, i.e. a new record may start
+    let mut separated = true;
+
+    for (i, line) in data.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        if line.trim().is_empty() {
+            separated = true;
+            continue;
+        }
+
+        let Some((tag, value)) = split_tag(line) else {
+            match records.last_mut() {
+                // long values are wrapped onto lines indented by six spaces
+                Some(Record { fields }) if !separated && line.starts_with(' ') => {
+                    let (_, value) = fields.last_mut().expect("records are never empty");
+                    value.push(' ');
+                    value.push_str(line.trim());
+                    continue;
+                }
+                _ => {
+                    let column = line.len() - line.trim_start().len() + 1;
+                    return Err(DecodeError::at(
+                        "expected a tag of the form `XXXX- `",
+                        (line_no, column),
+                    ));
+                }
+            }
+        };
+
+        if separated {
+            if tag != "PMID" {
+                return Err(DecodeError::at(
+                    format!("expected a `PMID` tag to start a record, found `{tag}`"),
+                    (line_no, 1),
+                ));
+            }
+            records.push(Record { fields: Vec::new() });
+            separated = false;
+        }
+        let record = records.last_mut().expect("a record was started");
+        record.fields.push((tag.to_string(), value.to_string()));
+    }
+
+    Ok(records)
+}
+
+/// Splits a line of the form `XXXX- value` into tag and value; shorter tags are padded with
+/// spaces.
+fn split_tag(line: &str) -> Option<(&str, &str)> {
+    let tag = line.get(..4)?.trim_end();
+    let value = line.get(4..)?.strip_prefix('-')?;
+    if tag.is_empty()
+        || !tag
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+    {
+        return None;
+    }
+    Some((tag, value.trim()))
+}
+
+fn convert_record(record: &Record) -> EntryBuilder {
+    // records from the NCBI Bookshelf carry the book's title in `BTI`
+    let book_title = record.get("BTI");
+    let mut entry = match (record.get("TI"), book_title) {
+        (Some(_), Some(book_title)) => {
+            let mut entry = EntryBuilder::new("chapter");
+            entry.parent("book").set_text("title", book_title);
+            entry
+        }
+        (None, Some(_)) => EntryBuilder::new("book"),
+        _ => EntryBuilder::new("article"),
+    };
+
+    if let Some(title) = record.get("TI").or(book_title) {
+        entry.set_text("title", title);
+    }
+
+    // `FAU` contains the full name, `AU` only initials; prefer the former if present
+    let authors = if record.get("FAU").is_some() {
+        record.all("FAU")
+    } else {
+        record.all("AU")
+    };
+    for author in authors {
+        entry.push("author", person(author));
+    }
+    let editors = if record.get("FED").is_some() {
+        record.all("FED")
+    } else {
+        record.all("ED")
+    };
+    for editor in editors {
+        entry.push("editor", person(editor));
+    }
+
+    if let Some(date) = record.get("DP").and_then(builder::date_from_str) {
+        entry.set("date", date);
+    }
+
+    if let Some(pmid) = record.get("PMID") {
+        entry.set_serial_number("pmid", pmid);
+    }
+    if let Some(pmcid) = record.get("PMC") {
+        entry.set_serial_number("pmcid", pmcid);
+    }
+    if let Some(doi) = record.article_id("doi") {
+        entry.set_serial_number("doi", doi);
+    }
+    if let Some(isbn) = record.get("ISBN") {
+        entry.set_serial_number("isbn", isbn);
+    }
+
+    if let Some(pages) = record.get("PG") {
+        entry.set_text("page-range", pages);
+    }
+    if let Some(value) = record.get("AB") {
+        entry.set_text("abstract", value);
+    }
+    if let Some(language) = record.get("LA") {
+        entry.set_language(language_code(language));
+    }
+    if let Some(name) = record.get("PB") {
+        entry.set_publisher(Some(name), record.get("PL"));
+    }
+
+    // journal articles: the journal with its volume and issue is the parent
+    if book_title.is_none() {
+        if let Some(journal) = record.get("JT").or_else(|| record.get("TA")) {
+            let parent = entry.parent("periodical");
+            let short = record.get("TA").filter(|short| *short != journal);
+            parent.set_title(journal, short);
+            for (tag, field) in [("VI", "volume"), ("IP", "issue")] {
+                if let Some(value) = record.get(tag) {
+                    parent.set_text(field, value);
+                }
+            }
+            // the print or electronic ISSN, e.g. `1234-5678 (Print)`
+            if let Some(issn) = record.get("IS") {
+                let issn = issn.split_whitespace().next().unwrap_or_default();
+                parent.set_serial_number("issn", issn);
+            }
+        }
+    }
+
+    entry
+}
+
+/// Creates a person from `FAU` names (`Family, Given`) or `AU` names (`Family Initials`).
+fn person(name: &str) -> serde_yaml::Value {
+    if name.contains(',') {
+        return builder::person_from_str(name);
+    }
+    match name.rsplit_once(' ') {
+        Some((family, initials)) if initials.chars().all(|c| c.is_uppercase() || c == '-') => {
+            builder::person(family, Some(initials), None, None)
+        }
+        // collective authors such as "WHO Study Group"
+        _ => builder::person(name, None, None, None),
+    }
+}
+
+/// Maps the three-letter language codes used by MEDLINE to the corresponding two-letter codes.
+fn language_code(code: &str) -> &str {
+    match code {
+        "eng" => "en",
+        "ger" => "de",
+        "fre" => "fr",
+        "spa" => "es",
+        "ita" => "it",
+        "por" => "pt",
+        "dut" => "nl",
+        "rus" => "ru",
+        "jpn" => "ja",
+        "chi" => "zh",
+        "pol" => "pl",
+        "swe" => "sv",
+        "dan" => "da",
+        "nor" => "no",
+        "tur" => "tr",
+        _ => code,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        let data = "PMID- 12345678\n\
+            OWN - NLM\n\
+            TI  - At-scale impact of the Net Wok: a culinarically holistic investigation\n      \
+            of distributed dumplings.\n\
+            FAU - Astley, Rick\n\
+            AU  - Astley R\n\
+            FAU - Morris, Linda\n\
+            AU  - Morris L\n\
+            LA  - eng\n\
+            DP  - 2020 Mar 4\n\
+            TA  - Armen J Proc\n\
+            JT  - Armenian Journal of Proceedings\n\
+            VI  - 61\n\
+            IP  - 2\n\
+            PG  - 192-219\n\
+            LID - 10.1000/netwok [doi]\n\
+            PMC - PMC7654321\n\
+            \n\
+            PMID- 23456789\n\
+            TI  - A second article.\n\
+            AU  - Astley R\n\
+            DP  - 2020\n";
+        let library = from_nbib_str(data).unwrap();
+
+        let keys = library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["astley2020", "astley2020a"]);
+
+        let entry = library.get("astley2020").unwrap();
+        assert!(entry
+            .title()
+            .unwrap()
+            .value
+            .to_str()
+            .ends_with("investigation of distributed dumplings."));
+        assert_eq!(entry.authors().unwrap().len(), 2);
+        assert_eq!(entry.pmid(), Some("12345678"));
+        assert_eq!(entry.pmcid(), Some("PMC7654321"));
+        assert_eq!(entry.doi(), Some("10.1000/netwok"));
+        assert!(entry.parents()[0].issue().is_some());
+
+        let entry = library.get("astley2020a").unwrap();
+        let author = &entry.authors().unwrap()[0];
+        assert_eq!(author.given_name.as_deref(), Some("R"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = from_nbib_str("TI  - No PMID\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "expected a `PMID` tag to start a record, found `TI`"
+        );
+        assert_eq!(err.location, Some((1, 1)));
+    }
+}
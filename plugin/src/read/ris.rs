@@ -0,0 +1,309 @@
+//! Decoding of RIS, the tagged format exported by most library catalogues and publishers.
+//! See https://en.wikipedia.org/wiki/RIS_(file_format)
+
+use hayagriva::Library;
+
+use super::builder::{self, EntryBuilder, KeyGenerator};
+use super::DecodeError;
+
+/// A single record between a `TY` and an `ER` tag.
+#[derive(Debug, Clone, PartialEq)]
+struct Record {
+    fields: Vec<(String, String)>,
+}
+
+impl Record {
+    fn get(&self, tag: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, value)| t == tag && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn all<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a str> {
+        self.fields
+            .iter()
+            .filter(move |(t, value)| t == tag && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn first(&self, tags: &[&str]) -> Option<&str> {
+        tags.iter().find_map(|tag| self.get(tag))
+    }
+}
+
+/// Parses RIS records into a library.
+///
+/// Entries are keyed by their `ID` tag. Records without one get a key generated by
+/// [`KeyGenerator`].
+pub fn from_ris_str(data: &str) -> Result<Library, DecodeError> {
+    let records = parse(data)?;
+
+    let mut keys = KeyGenerator::new(records.iter().filter_map(|record| record.get("ID")));
+
+    let entries = records.iter().map(|record| {
+        let key = match record.get("ID") {
+            Some(id) => id.to_string(),
+            None => generate_key(record, &mut keys),
+        };
+        (key, convert_record(record))
+    });
+
+    Ok(builder::into_library(entries.collect::<Vec<_>>())?)
+}
+
+/// Splits the data into records, reporting structural errors with their location.
+fn parse(data: &str) -> Result<Vec<Record>, DecodeError> {
+    let mut records = Vec::new();
+    let mut current: Option<(usize, Record)> = None;
+
+    for (i, line) in data.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let Some((tag, value)) = split_tag(line) else {
+            match &mut current {
+                // long values are sometimes wrapped onto continuation lines
+                Some((_, Record { fields })) if !fields.is_empty() => {
+                    let (_, value) = fields.last_mut().unwrap();
+                    value.push(' ');
+                    value.push_str(line.trim());
+                    continue;
+                }
+                _ => {
+                    let column = line.len() - line.trim_start().len() + 1;
+                    return Err(DecodeError::at(
+                        "expected a tag of the form `XX  - `",
+                        (line_no, column),
+                    ));
+                }
+            }
+        };
+
+        match (tag, &mut current) {
+            ("TY", Some((start, _))) => {
+                return Err(DecodeError::at(
+                    format!("record starting at line {start} is not terminated by an `ER` tag"),
+                    (line_no, 1),
+                ));
+            }
+            ("TY", None) => {
+                let fields = vec![(tag.to_string(), value.to_string())];
+                current = Some((line_no, Record { fields }));
+            }
+            ("ER", Some(_)) => {
+                let (_, record) = current.take().unwrap();
+                records.push(record);
+            }
+            (_, None) => {
+                return Err(DecodeError::at(
+                    format!("expected a `TY` tag to start a record, found `{tag}`"),
+                    (line_no, 1),
+                ));
+            }
+            (_, Some((_, record))) => {
+                record.fields.push((tag.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    if let Some((start, _)) = current {
+        return Err(DecodeError::at(
+            "record is not terminated by an `ER` tag",
+            (start, 1),
+        ));
+    }
+
+    Ok(records)
+}
+
+/// Splits a line of the form `XX  - value` into tag and value.
+fn split_tag(line: &str) -> Option<(&str, &str)> {
+    let tag = line.get(..2)?;
+    let rest = line.get(2..)?;
+    if !tag
+        .bytes()
+        .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit())
+    {
+        return None;
+    }
+    let value = rest.strip_prefix("  -")?;
+    Some((tag, value.trim()))
+}
+
+/// Maps a RIS reference type to a Hayagriva entry type and, if applicable, the type of its
+/// container.
+fn entry_types(kind: &str) -> (&'static str, Option<&'static str>) {
+    match kind {
+        "JOUR" | "JFULL" | "EJOUR" | "MGZN" | "INPR" => ("article", Some("periodical")),
+        "NEWS" => ("article", Some("newspaper")),
+        "CONF" | "CPAPER" => ("article", Some("proceedings")),
+        "BLOG" => ("article", Some("blog")),
+        "CHAP" | "ECHAP" => ("chapter", Some("book")),
+        "ENCYC" | "DICT" => ("entry", Some("reference")),
+        "BOOK" | "EBOOK" | "CLSWK" => ("book", None),
+        "EDBOOK" | "ANTH" => ("anthology", None),
+        "RPRT" | "STAND" | "GOVDOC" => ("report", None),
+        "THES" => ("thesis", None),
+        "UNPB" | "MANSCPT" => ("manuscript", None),
+        "ELEC" | "WEB" => ("web", None),
+        "DATA" | "DBASE" | "COMP" => ("repository", None),
+        "PAT" => ("patent", None),
+        "CASE" => ("case", None),
+        "BILL" | "STAT" | "LEGAL" => ("legislation", None),
+        "VIDEO" | "MPCT" | "ADVS" => ("video", None),
+        "SOUND" | "MUSIC" => ("audio", None),
+        "ART" | "FIGURE" | "MAP" => ("artwork", None),
+        _ => ("misc", None),
+    }
+}
+
+fn convert_record(record: &Record) -> EntryBuilder {
+    let kind = record.get("TY").unwrap_or_default();
+    let (entry_type, container_type) = entry_types(kind);
+    let mut entry = EntryBuilder::new(entry_type);
+
+    if let Some(title) = record.first(&["TI", "T1", "CT"]) {
+        entry.set_title(title, record.get("ST"));
+    }
+    for author in record.all("AU").chain(record.all("A1")) {
+        entry.push("author", builder::person_from_str(author));
+    }
+    for editor in record.all("ED").chain(record.all("A2")) {
+        entry.push("editor", builder::person_from_str(editor));
+    }
+    for translator in record.all("A4") {
+        entry.push_affiliated("translator", builder::person_from_str(translator));
+    }
+
+    // `DA` is the full date, `PY` and `Y1` often only contain the year
+    if let Some(date) = ["DA", "PY", "Y1"]
+        .iter()
+        .filter_map(|tag| record.get(tag))
+        .find_map(builder::date_from_str)
+    {
+        entry.set("date", date);
+    }
+    if let Some(url) = record.first(&["UR", "L2"]) {
+        let accessed = record.get("Y2").and_then(builder::date_from_str);
+        entry.set_url(url, accessed);
+    }
+
+    if let Some(doi) = record.get("DO") {
+        entry.set_serial_number("doi", doi);
+    }
+    if let Some(number) = record.get("SN") {
+        // the same tag is used for ISBNs and ISSNs, tell them apart by their length
+        let digits = number.chars().filter(char::is_ascii_alphanumeric).count();
+        let kind = if digits == 8 { "issn" } else { "isbn" };
+        entry.set_serial_number(kind, number);
+    }
+
+    match (record.get("SP"), record.get("EP")) {
+        (Some(start), Some(end)) => entry.set_text("page-range", &format!("{start}-{end}")),
+        (Some(pages), None) => entry.set_text("page-range", pages),
+        _ => {}
+    }
+    for (tag, field) in [
+        ("ET", "edition"),
+        ("AB", "abstract"),
+        ("N2", "abstract"),
+        ("N1", "note"),
+        ("M3", "genre"),
+        ("CN", "call-number"),
+    ] {
+        if let Some(value) = record.get(tag) {
+            if !entry.has(field) {
+                entry.set_text(field, value);
+            }
+        }
+    }
+    if let Some(language) = record.get("LA") {
+        entry.set_language(language);
+    }
+
+    entry.set_publisher(record.get("PB"), record.get("CY"));
+
+    // volume and issue describe the container if there is one, the entry itself otherwise
+    let container_title = record.first(&["T2", "JO", "JF", "JA", "BT"]);
+    let target = match (container_type, container_title) {
+        (Some(container_type), Some(container_title)) => {
+            let parent = entry.parent(container_type);
+            parent.set_text("title", container_title);
+            parent
+        }
+        _ => &mut entry,
+    };
+    for (tag, field) in [("VL", "volume"), ("IS", "issue")] {
+        if let Some(value) = record.get(tag) {
+            target.set_text(field, value);
+        }
+    }
+
+    entry
+}
+
+/// Generates a key for a record without an `ID` tag.
+fn generate_key(record: &Record, keys: &mut KeyGenerator) -> String {
+    let name = record
+        .first(&["AU", "A1", "ED", "A2"])
+        .and_then(|author| author.split(',').next())
+        .or_else(|| record.first(&["TI", "T1"]));
+    let date = record.first(&["PY", "Y1", "DA"]);
+    keys.generate(name, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records() {
+        let data = "TY  - JOUR\n\
+            AU  - Astley, Rick\n\
+            AU  - Morris, Linda\n\
+            TI  - At-scale impact of the Net Wok\n\
+            T2  - Armenian Journal of Proceedings\n\
+            PY  - 2020/03/04/\n\
+            VL  - 61\n\
+            SP  - 192\n\
+            EP  - 219\n\
+            ER  - \n\
+            \n\
+            TY  - BOOK\n\
+            AU  - Astley, Rick\n\
+            TI  - Never gonna give you up\n\
+            PY  - 2020\n\
+            ER  - \n\
+            TY  - BOOK\n\
+            ID  - manual\n\
+            TI  - A manually keyed book\n\
+            ER  - \n";
+        let library = from_ris_str(data).unwrap();
+
+        let keys = library.keys().collect::<Vec<_>>();
+        assert_eq!(keys, ["astley2020", "astley2020a", "manual"]);
+        let entry = library.get("astley2020").unwrap();
+        assert_eq!(entry.authors().unwrap().len(), 2);
+        assert_eq!(entry.date().unwrap().day, Some(3));
+        assert!(entry.page_range().is_some());
+        assert!(entry.parents()[0].volume().is_some());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = from_ris_str("TY  - JOUR\nTI  - Unterminated\n").unwrap_err();
+        assert_eq!(err.message, "record is not terminated by an `ER` tag");
+        assert_eq!(err.location, Some((1, 1)));
+
+        let err = from_ris_str("TY  - JOUR\nER  - \nTI  - Stray\n").unwrap_err();
+        assert_eq!(
+            err.message,
+            "expected a `TY` tag to start a record, found `TI`"
+        );
+        assert_eq!(err.location, Some((3, 1)));
+    }
+}
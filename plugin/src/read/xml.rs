@@ -0,0 +1,201 @@
+//! A minimal XML element tree for the XML-based bibliography formats. Namespace prefixes are
+//! dropped from element and attribute names, since the formats read here don't mix elements of
+//! the same name from different namespaces.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::{line_column, DecodeError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Element(Element),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Element {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<Node>,
+}
+
+impl Element {
+    /// Returns the value of the attribute with the given name.
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the child elements.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    /// Returns the child elements with the given name.
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |element| element.name == name)
+    }
+
+    /// Returns the first child element with the given name.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Follows a path of child element names and returns the first match.
+    pub fn find(&self, path: &[&str]) -> Option<&Element> {
+        path.iter()
+            .try_fold(self, |element, name| element.child(name))
+    }
+
+    /// Returns this element and all its descendants with the given name, in document order.
+    pub fn descendants<'a>(&'a self, name: &str) -> Vec<&'a Element> {
+        fn collect<'a>(element: &'a Element, name: &str, result: &mut Vec<&'a Element>) {
+            if element.name == name {
+                result.push(element);
+            }
+            for child in element.elements() {
+                collect(child, name, result);
+            }
+        }
+
+        let mut result = Vec::new();
+        collect(self, name, &mut result);
+        result
+    }
+
+    /// Returns the text of this element and its descendants with whitespace collapsed, or
+    /// `None` if it is blank.
+    pub fn text(&self) -> Option<String> {
+        fn collect(element: &Element, text: &mut String) {
+            for node in &element.children {
+                match node {
+                    Node::Element(element) => collect(element, text),
+                    Node::Text(t) => text.push_str(t),
+                }
+            }
+        }
+
+        let mut text = String::new();
+        collect(self, &mut text);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Returns the text of the element at the given path, see [`Self::find`] and [`Self::text`].
+    pub fn text_at(&self, path: &[&str]) -> Option<String> {
+        self.find(path).and_then(Element::text)
+    }
+}
+
+/// Parses an XML document and returns its root element.
+pub fn parse(data: &str) -> Result<Element, DecodeError> {
+    let mut reader = Reader::from_str(data);
+    reader.config_mut().expand_empty_elements = true;
+
+    let error = |position: u64, message: String| {
+        let location = line_column(data, position as usize).unwrap_or((1, 1));
+        DecodeError::at(message, location)
+    };
+
+    let mut stack: Vec<Element> = Vec::new();
+    loop {
+        let position = reader.buffer_position();
+        let event = reader
+            .read_event()
+            .map_err(|err| error(reader.error_position(), err.to_string()))?;
+        match event {
+            Event::Start(start) => {
+                let element = start_element(&start).map_err(|err| error(position, err))?;
+                stack.push(element);
+            }
+            Event::End(_) => {
+                let element = stack.pop().expect("end tags are checked by the reader");
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(Node::Element(element)),
+                    None => return Ok(element),
+                }
+            }
+            Event::Text(text) => {
+                let text = text
+                    .xml_content()
+                    .map_err(|err| error(position, err.to_string()))?;
+                push_text(&mut stack, &text);
+            }
+            Event::CData(text) => {
+                let text = text
+                    .decode()
+                    .map_err(|err| error(position, err.to_string()))?;
+                push_text(&mut stack, &text);
+            }
+            Event::GeneralRef(reference) => {
+                let text = match reference
+                    .resolve_char_ref()
+                    .map_err(|err| error(position, err.to_string()))?
+                {
+                    Some(c) => c.to_string(),
+                    None => {
+                        let name = reference
+                            .decode()
+                            .map_err(|err| error(position, err.to_string()))?;
+                        quick_xml::escape::resolve_predefined_entity(&name)
+                            .ok_or_else(|| error(position, format!("unknown entity `&{name};`")))?
+                            .to_string()
+                    }
+                };
+                push_text(&mut stack, &text);
+            }
+            Event::Eof => return Err(error(position, "missing root element".to_string())),
+            Event::Empty(_)
+            | Event::Comment(_)
+            | Event::Decl(_)
+            | Event::PI(_)
+            | Event::DocType(_) => {}
+        }
+    }
+}
+
+fn start_element(start: &BytesStart) -> Result<Element, String> {
+    let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+    let attributes = start
+        .attributes()
+        .map(|attribute| {
+            let attribute = attribute.map_err(|err| err.to_string())?;
+            let name = String::from_utf8_lossy(attribute.key.local_name().as_ref()).into_owned();
+            let value = attribute.unescape_value().map_err(|err| err.to_string())?;
+            Ok((name, value.into_owned()))
+        })
+        .collect::<Result<_, String>>()?;
+    Ok(Element {
+        name,
+        attributes,
+        children: Vec::new(),
+    })
+}
+
+fn push_text(stack: &mut [Element], text: &str) {
+    // text outside the root element can only be whitespace
+    let Some(element) = stack.last_mut() else {
+        return;
+    };
+    match element.children.last_mut() {
+        Some(Node::Text(previous)) => previous.push_str(text),
+        _ => element.children.push(Node::Text(text.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed() {
+        let err = parse("<xml>\n<records></xml>").unwrap_err();
+        assert_eq!(err.location.map(|(line, _)| line), Some(2));
+    }
+}
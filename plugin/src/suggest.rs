@@ -0,0 +1,166 @@
+//! Suggestions for misspelled citation keys and style names.
+
+use hayagriva::Entry;
+use indexmap::IndexMap;
+
+/// The maximum number of suggestions.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Suggests the keys of entries that were probably meant instead of an unknown key. Keys are
+/// ranked by their edit distance, and by how many of the key's words and numbers match the key,
+/// the authors and the year of an entry, so that e.g. `smith2021` also finds `Smith_2020_Nature`.
+/// Keys that are not similar are only suggested if they share a word, since a year alone matches
+/// too many entries.
+pub fn suggest_keys(key: &str, entries: &IndexMap<String, Entry>) -> Vec<String> {
+    let candidates = entries.iter().map(|(candidate, entry)| {
+        let mut tokens = tokens(candidate);
+        for author in entry.authors().unwrap_or_default() {
+            tokens.extend(self::tokens(&author.name));
+        }
+        if let Some(date) = entry.date() {
+            tokens.push(date.year.to_string());
+        }
+        (candidate.as_str(), tokens)
+    });
+    rank(key, candidates)
+}
+
+/// Suggests the names that were probably meant instead of an unknown name.
+pub fn suggest_names<'a>(name: &str, names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    rank(name, names.into_iter().map(|name| (name, tokens(name))))
+}
+
+/// Formats suggestions to be appended to an error message.
+pub fn did_you_mean(suggestions: &[String]) -> String {
+    let quoted = suggestions
+        .iter()
+        .map(|suggestion| format!("`{suggestion}`"))
+        .collect::<Vec<_>>();
+    match quoted.as_slice() {
+        [] => String::new(),
+        [one] => format!(" (did you mean {one}?)"),
+        [init @ .., last] => format!(" (did you mean {} or {last}?)", init.join(", ")),
+    }
+}
+
+fn rank<'a>(query: &str, candidates: impl Iterator<Item = (&'a str, Vec<String>)>) -> Vec<String> {
+    let lowercase = query.to_lowercase();
+    let query_tokens = tokens(query);
+    // allow about one typo per three characters
+    let max_distance = (query.chars().count() / 3).max(1);
+
+    let mut ranked = candidates
+        .filter_map(|(candidate, tokens)| {
+            let distance = distance(&lowercase, &candidate.to_lowercase());
+            let shared = query_tokens
+                .iter()
+                .filter(|token| tokens.contains(token))
+                .collect::<Vec<_>>();
+            let shares_word = shared.iter().any(|token| !is_number(token));
+            if distance > max_distance && !shares_word {
+                return None;
+            }
+            // every shared word or number counts like two characters being right
+            let score = distance as isize - 2 * shared.len() as isize;
+            Some((score, distance, candidate))
+        })
+        .collect::<Vec<_>>();
+    ranked.sort();
+    ranked.dedup_by_key(|(_, _, candidate)| *candidate);
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, _, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// Splits a string into lowercase words and numbers, ignoring words of less than three letters.
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    for c in text.chars().chain([' ']) {
+        let continues = match token.chars().last() {
+            Some(last) => c.is_numeric() == last.is_numeric() && c.is_alphanumeric(),
+            None => true,
+        };
+        if !continues {
+            if is_number(&token) || token.chars().count() >= 3 {
+                tokens.push(token.to_lowercase());
+            }
+            token.clear();
+        }
+        if c.is_alphanumeric() {
+            token.push(c);
+        }
+    }
+    tokens
+}
+
+fn is_number(token: &str) -> bool {
+    token.chars().all(char::is_numeric)
+}
+
+/// The Levenshtein distance of two strings.
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(a != b);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_keys() {
+        let yaml = r#"
+            smith2020:
+              type: article
+              author: Smith, John
+              date: 2020
+            Nature_2021:
+              type: article
+              author: Smith, Jane
+              date: 2021
+            netwok:
+              type: article
+        "#;
+        let library = hayagriva::io::from_yaml_str(yaml).unwrap();
+        let entries = library
+            .into_iter()
+            .map(|entry| (entry.key().to_string(), entry))
+            .collect();
+
+        assert_eq!(suggest_keys("smtih2020", &entries), ["smith2020"]);
+        assert_eq!(
+            suggest_keys("smith2021", &entries),
+            ["smith2020", "Nature_2021"]
+        );
+        assert!(suggest_keys("dumplings", &entries).is_empty());
+        // sharing only the year is not enough
+        assert!(suggest_keys("jones2020", &entries).is_empty());
+    }
+
+    #[test]
+    fn test_suggest_names() {
+        let names = ["ieee", "apa", "chicago-author-date", "chicago-notes"];
+        assert_eq!(suggest_names("iee", names), ["ieee"]);
+        assert_eq!(
+            suggest_names("chicago", names),
+            ["chicago-notes", "chicago-author-date"]
+        );
+        assert_eq!(
+            did_you_mean(&suggest_names("chicago", names)),
+            " (did you mean `chicago-notes` or `chicago-author-date`?)"
+        );
+    }
+}
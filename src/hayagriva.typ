@@ -7,18 +7,60 @@
   decode(_p.names())
 }
 
-#let read(
-  sources,
-  full,
-  style,
-  locale,
-  citations,
-) = {
+// the message to report an error from the plugin with. Errors are dictionaries with a `kind`
+// (e.g. `"parse"`, `"missing-key"` or `"unknown-style"`), a `message`, and, where known, the `path`,
+// `line` and `column`, the offending `key`, `suggestions` and the configuration `field` that could
+// not be decoded, and all `errors` if there were several.
+#let error-message(error) = {
+  let message = "Alexandria: " + error.message
+  if error.path != none and error.line != none and not error.message.contains(error.path) {
+    message += " (" + error.path + ":" + str(error.line) + ":" + str(error.column) + ")"
+  }
+  if error.field != none and not error.message.contains(error.field) {
+    message += " (in `" + error.field + "`)"
+  }
+  if error.suggestions.len() > 0 and not error.message.contains("did you mean") {
+    message += " (did you mean " + error.suggestions.map(s => "`" + s + "`").join(", ", last: " or ") + "?)"
+  }
+  // the combined message only describes the first error; `panic()` shows messages on one line
+  if error.errors.len() > 1 {
+    message += ": " + error.errors.map(error => error.message).join("; ")
+  }
+  if error.kind == "missing-key" {
+    message += "; hint: pass `missing: \"placeholder\"` to render missing keys as placeholders"
+  } else if error.kind == "parse" and error.errors.len() > 1 {
+    message += "; hint: pass `skip-invalid: true` to leave out invalid entries"
+  }
+  message
+}
+
+// reads the bibliography for the given configuration, a dictionary with the fields `sources`,
+// `full`, `nocite`, `style`, `locale`, `doi-keys`, `duplicates`, `deduplicate`,
+// `case-insensitive-keys`, `missing`, `skip-invalid`, `lint` and `citations`
+#let read(config) = {
   // Typst 0.13: `cbor.decode` is deprecated, directly pass bytes to `cbor` instead
   let decode = if sys.version < version(0, 13, 0) { cbor.decode } else { cbor }
 
-  let config = cbor.encode((sources: sources, full: full, style: style, locale: locale, citations: citations))
-  decode(_p.read(config))
+  let config = cbor.encode(config)
+  let response = decode(_p.read(config))
+  if "error" in response {
+    panic(error-message(response.error))
+  }
+  response.ok
+}
+
+// checks the cited entries against the variables the style uses, without rendering them, for a
+// configuration as for `read`; returns the diagnostics
+#let lint(config) = {
+  // Typst 0.13: `cbor.decode` is deprecated, directly pass bytes to `cbor` instead
+  let decode = if sys.version < version(0, 13, 0) { cbor.decode } else { cbor }
+
+  let config = cbor.encode(config)
+  let response = decode(_p.lint(config))
+  if "error" in response {
+    panic(error-message(response.error))
+  }
+  response.ok
 }
 
 #let render(body, keys: none, ..transparent-contents) = {
@@ -1,3 +1,5 @@
+#import "hayagriva.typ"
+
 #let locale() = {
   let locale = text.lang
   if text.region != none { locale += "-" + text.region }
@@ -11,3 +13,86 @@
   assert.ne(csl, "..", message: "only named CSL styles can be converted to strings")
   csl
 }
+
+// the text of content consisting only of text and spaces, or `none`
+#let plain-text(it) = {
+  if type(it) == str { return it }
+  if it.func() == text { return it.text }
+  if it.func() == [ ].func() { return " " }
+  if it.func() == [].func() {
+    let parts = it.children.map(plain-text)
+    if parts.len() > 0 and parts.all(part => part != none) { return parts.join() }
+  }
+  none
+}
+
+// converts a citation's supplement for the plugin: locators created by `locator()` are passed as
+// dictionaries, other supplements as their text, from which locators such as "p. 5" are parsed, or
+// as their `repr` if they are not plain text, which still identifies them
+#let supplement-to-plugin(supplement) = {
+  if supplement == none { return none }
+  if type(supplement) == dictionary { return supplement }
+  if supplement.func() == metadata and type(supplement.value) == dictionary and "locator" in supplement.value {
+    return supplement.value
+  }
+  let plain = plain-text(supplement)
+  if plain != none { plain } else { repr(supplement) }
+}
+
+// converts a citation's prefix or suffix for the plugin, which only supports plain text
+#let affix-to-plugin(affix) = {
+  if affix == none { return none }
+  let plain = plain-text(affix)
+  assert.ne(plain, none, message: "citation prefixes and suffixes must be plain text")
+  plain
+}
+
+// the configuration of the plugin for the bibliography in `path`, as a function of the citations.
+// The named `options` are passed to the plugin as they are, apart from those that are converted
+// here. This function is contextual.
+#let plugin-config(path, style, format: auto, encoding: auto, nocite: (), ..options) = {
+  import "state.typ": read
+
+  let path = path
+  if type(path) != array {
+    path = (path,)
+  }
+
+  let sources = path.map(path => {
+    if type(path) == dictionary {
+      // entries given directly, keyed by their citation keys
+      return (entries: path)
+    }
+    let source = read(path)
+    if format != auto {
+      source.format = format
+    }
+    if encoding != auto {
+      source.encoding = encoding
+    }
+    source
+  })
+
+  let style = csl-to-string(style)
+  if style in hayagriva.names {
+    style = (built-in: style)
+  } else {
+    let data = read(style).data
+    style = (custom: if type(data) == bytes { str(data) } else { data })
+  }
+
+  let locale = locale()
+  citations => (
+    sources: sources,
+    nocite: nocite.map(str),
+    style: style,
+    locale: locale,
+    ..options.named(),
+    citations: citations.map(group => group.map(citation => (
+      ..citation,
+      supplement: supplement-to-plugin(citation.supplement),
+      prefix: affix-to-plugin(citation.prefix),
+      suffix: affix-to-plugin(citation.suffix),
+    ))),
+  )
+}
@@ -6,13 +6,27 @@
 
   assert(str(key).starts-with(prefix), message: "Can only refer to an entry with the given prefix.")
 
+  // the options given to `citex()` are wrapped around the actual supplement, since `cite()` can't
+  // carry them otherwise
+  let (form, supplement) = (form, supplement)
+  let affixes = (prefix: none, suffix: none)
+  if type(supplement) == content and supplement.func() == metadata and type(supplement.value) == dictionary and "citex" in supplement.value {
+    let citex = supplement.value.citex
+    (form, supplement) = (citex.form, supplement.value.supplement)
+    affixes = (prefix: citex.prefix, suffix: citex.suffix)
+  }
+
   let (index, group) = get-citation-info(prefix)
   add-citation(prefix, (
     key: str(key).slice(prefix.len()),
     form: form,
     ..if style != auto { (style: csl-to-string(style)) },
     supplement: supplement,
+    ..affixes,
     locale: locale(),
+    // the number of the footnote the citation becomes in note styles. Inside a footnote, this is
+    // the number of the following footnote, since that can't be told apart from the outside
+    note: counter(footnote).get().first() + 1,
   ))
   if not group {
     context {
@@ -37,6 +51,10 @@
 /// ```typ
 /// #show: alexandria(prefix: "x:", read: path => read(path))
 /// ```
+///
+/// In note styles, citations become footnotes, and their footnote numbers decide e.g. whether
+/// "ibid." applies. Citations written inside footnotes are counted as if they were in the next
+/// footnote, so such positions may be off for them.
 /// -> function
 #let alexandria(
   /// a prefix that identifies citations from a specific Alexandria's bibliography.
@@ -45,6 +63,9 @@
   /// the function to process the `path` and `style` parameters of @@bibliographyx() and
   /// @@load-bibliography() commands.
   /// Pass ```typc path => read(path)``` to read the contents of the bibliography and style files.
+  /// To load bibliographies that are not encoded in UTF-8, pass
+  /// ```typc path => read(path, encoding: none)``` instead so that the plugin receives the raw
+  /// bytes; see the `encoding` parameter of @@load-bibliography().
   /// -> function
   read: none,
 ) = body => {
@@ -86,6 +107,79 @@
   body
 }
 
+/// Cites an entry with text before and after it that is put inside the citation, e.g. within its
+/// parentheses:
+/// ```typ
+/// #citex(<x:netwok>, prefix: "see", suffix: ", for a review")
+/// ```
+/// In APA style, this is rendered as "(see Astley & Morris, 2020, for a review)". Besides the forms
+/// of `cite()`, citations can have the form `"suppress-author"` for sentences like "Astley and
+/// Morris argue (2020, p. 4)"; unlike `"year"`, it keeps the locator and everything else except
+/// the author. In styles that would print nothing but the author, such as MLA without a locator,
+/// the author is kept and a warning is listed in the `diagnostics` of @@get-bibliography(). Like
+/// citations using `cite()`, these can be part of a @@citegroup().
+///
+/// -> content
+#let citex(
+  /// the label of the entry to cite.
+  /// -> label
+  key,
+  /// text to put before the citation. It is separated from the citation by a space. Content must
+  /// be plain text, e.g. `[see]` but not `[_see_]`.
+  /// -> string | content | none
+  prefix: none,
+  /// text to put after the citation. It is separated from the citation by a space unless it starts
+  /// with punctuation, as in `", for a review"`. Like the prefix, it must be plain text.
+  /// -> string | content | none
+  suffix: none,
+  /// the supplement of the citation, as for `cite()`.
+  /// -> content | none
+  supplement: none,
+  /// the form of the citation: one of the forms of `cite()`, or `"suppress-author"`.
+  /// -> string | none
+  form: "normal",
+  /// the style of the citation, as for `cite()`.
+  /// -> string | auto
+  style: auto,
+) = {
+  import "internal.typ": plain-text
+
+  // the plugin only receives text, so formatting would be lost
+  for (name, affix) in (prefix: prefix, suffix: suffix) {
+    if affix != none and plain-text(affix) == none {
+      panic("Alexandria: the " + name + " of a citation must be plain text, but is " + repr(affix))
+    }
+  }
+
+  cite(
+    key,
+    supplement: metadata((
+      citex: (prefix: prefix, suffix: suffix, form: form),
+      supplement: supplement,
+    )),
+    form: if form == "suppress-author" { "normal" } else { form },
+    style: style,
+  )
+}
+
+/// Creates an explicit locator to be used as the supplement of a citation, e.g.
+/// ```typ
+/// @x:netwok[#locator("chapter", "2")]
+/// ```
+/// Supplements such as `[p. 5]`, `[pp. 3-7]`, `[chap. 2]` or `[§ 4]` are recognized without this,
+/// so that the style and language determine how they are rendered, e.g. as "S. 5" in German.
+/// Other supplements are rendered as given.
+///
+/// -> content
+#let locator(
+  /// the CSL locator type, e.g. `"page"`, `"chapter"`, `"section"` or `"paragraph"`.
+  /// -> string
+  name,
+  /// the value of the locator, e.g. `"5"` or `"3-7"`.
+  /// -> string
+  value,
+) = metadata((locator: name, value: value))
+
 /// Creates a group of collapsed citations. The citations are given as regular content, e.g.
 /// ```typ
 /// #citegroup[@x:a @x:b]
@@ -170,7 +264,13 @@
 #let load-bibliography(
   /// the path(s) to the bibliography file(s), which is passed to the `read` function
   /// registered via @@alexandria(), or its binary contents if no `read` function provided.
-  /// -> string | bytes | array
+  /// Entries can also be given directly as a dictionary mapping keys to their fields, which follow
+  /// the #link("https://github.com/typst/hayagriva/blob/main/docs/file-format.md")[Hayagriva YAML format], e.g.
+  /// ```typc (memo: (type: "report", title: "Internal memo", date: "2024-05"))```.
+  /// BibLaTeX files given together share their `@string` macros, `crossref` parents and `@xdata`
+  /// entries. Entries can be cited by the additional keys listed in their `ids` field, both in
+  /// BibLaTeX and Hayagriva YAML, e.g. to keep old keys working after renaming an entry.
+  /// -> string | bytes | dictionary | array
   path,
   /// the optional prefix for which the bibliography is loaded.
   /// It only needs to be specified if more than one prefix was registered.
@@ -180,20 +280,70 @@
   /// from `path` or only the ones cited in the document.
   /// -> boolean
   full: false,
+  /// the keys of further entries to include without citing them, e.g. for further reading. The
+  /// keys are given as in the bibliography file, without the prefix, e.g. `("smith2020",)`. Keys
+  /// that don't exist are handled according to `missing`.
+  /// -> array
+  nocite: (),
   /// the style of the bibliography. Either a #link("https://typst.app/docs/reference/model/bibliography/#parameters-style")[built-in style],
   /// a path to a CSL file passed to `read()` registered via @@alexandria(), or its binary
   /// contents.
   /// -> string | bytes
   style: "ieee",
+  /// the citation keys of entries from sources that identify entries by their DOI, such as
+  /// Crossref or DataCite JSON metadata. Entries whose DOI is not in this dictionary use the
+  /// lowercase DOI as their key.
+  /// -> dictionary
+  doi-keys: (:),
+  /// the format of the bibliography file(s). One of `"hayagriva"` (or `"yaml"`), `"biblatex"`
+  /// (or `"bibtex"`), `"csl-json"`, `"crossref"`, `"datacite"`, `"ris"`, `"endnote"`, `"mods"`,
+  /// `"jats"`, `"medline"` (or `"pubmed"`) and `"cff"`. If `auto`, the format is determined from
+  /// the file extension, or detected from the contents if the extension is ambiguous or unknown.
+  /// -> string | auto
+  format: auto,
+  /// the text encoding of the bibliography file(s) if they are given as bytes. One of `"utf-8"`,
+  /// `"utf-16"`, `"utf-16le"`, `"utf-16be"`, `"latin-1"` and `"windows-1252"`. If `auto`, a byte
+  /// order mark determines the encoding; otherwise, the data is decoded as UTF-8 if valid, and as
+  /// Windows-1252 (a superset of Latin-1) if not.
+  /// -> string | auto
+  encoding: auto,
+  /// how to handle keys that appear in more than one of the bibliography files. One of
+  /// `"error"`, `"first-wins"`, `"last-wins"` and `"namespace"`, which prefixes the duplicated
+  /// keys with the name of their file, e.g. `personal:smith2020` for `personal.bib`. Duplicates
+  /// that are resolved are reported in the `diagnostics` of @@get-bibliography().
+  /// -> string
+  duplicates: "error",
+  /// whether to merge entries describing the same work under different keys, e.g. `smith2020`
+  /// and `Smith_2020_Nature`. Entries are considered the same if they share a DOI, ISBN or arXiv
+  /// identifier, or their title and year. Merged entries are cited as the first of them, but can
+  /// still be cited by any of their keys; the merges are listed in the `merges` of
+  /// @@get-bibliography().
+  /// -> boolean
+  deduplicate: false,
+  /// whether citation keys are looked up ignoring their case if there is no exact match, e.g. so
+  /// that `@x:Smith2020` finds the entry `smith2020`.
+  /// -> boolean
+  case-insensitive-keys: false,
+  /// how to handle citations of keys that are not in the bibliography. With `"error"`, the
+  /// document fails to compile; with `"placeholder"`, such citations are rendered as e.g.
+  /// `[key?]` and listed in the `missing` keys of @@get-bibliography(), which is convenient while
+  /// drafting.
+  /// -> string
+  missing: "error",
+  /// whether to skip invalid BibLaTeX entries instead of failing. All invalid entries are
+  /// reported either way; if they are skipped, they are listed in the `diagnostics` of
+  /// @@get-bibliography() and the rest of the bibliography is loaded.
+  /// -> boolean
+  skip-invalid: false,
+  /// whether to check the cited entries against the variables the style uses, e.g. that an
+  /// entry has a date if the style would otherwise print "n.d.", or that an article has a
+  /// journal. Problems are reported as warnings in the `diagnostics` of @@get-bibliography().
+  /// -> boolean
+  lint: false,
 ) = {
   import "state.typ": *
   import "internal.typ": *
 
-  let path = path
-  if type(path) != array {
-    path = (path,)
-  }
-
   context {
     let prefix = prefix
     if prefix == auto {
@@ -201,26 +351,22 @@
       assert.ne(prefix, none, message: "when using multiple custom bibliographies, you must specify the prefix for each")
     }
 
-    let sources = path.map(path => read(path))
-
-    let style = csl-to-string(style)
-    if style in hayagriva.names {
-      style = (built-in: style)
-    } else {
-      style = (custom: read(style).data)
-    }
-
-    let locale = locale()
-    set-bibliography(prefix, citations => hayagriva.read(
-      sources,
-      full,
+    let config = plugin-config(
+      path,
       style,
-      locale,
-      citations.map(group => group.map(((supplement, ..citation)) => {
-        let supplement = if supplement != none { repr(supplement) }
-        (..citation, supplement: supplement)
-      })),
-    ))
+      format: format,
+      encoding: encoding,
+      full: full,
+      nocite: nocite,
+      doi-keys: doi-keys,
+      duplicates: duplicates,
+      deduplicate: deduplicate,
+      case-insensitive-keys: case-insensitive-keys,
+      missing: missing,
+      skip-invalid: skip-invalid,
+      lint: lint,
+    )
+    set-bibliography(prefix, citations => hayagriva.read(config(citations)))
   }
 }
 
@@ -239,9 +385,23 @@
 ///   citations in the document. The array is sorted by the appearance of citations in the document.
 /// - `hanging-indent`: a boolean indicating whether the citation style uses a hanging indent for
 ///   its entries.
+/// - `diagnostics`: an array of problems that did not prevent loading the bibliography, such as
+///   resolved duplicate keys, unknown fields, dates that could not be parsed, unsupported LaTeX
+///   commands, a missing locale, invalid entries that were skipped, `"suppress-author"` citations
+///   that keep their author, or, with `lint`, variables the style uses that cited entries don't
+///   have. Each has a `severity` (`"error"` if something was
+///   left out, `"warning"` otherwise) and a `message`, and where known the `key` of the entry and
+///   the `path`, `line` and `column` in its file.
+/// - `merges`: an array of entries that were merged into another one when deduplicating, with
+///   the fields `key`, `into` (the canonical key) and `reason` (one of `"doi"`, `"isbn"`,
+///   `"arxiv"` and `"title-year"`).
+/// - `missing`: an array of the cited keys that are not in the bibliography, if these are rendered
+///   as placeholders.
 ///
 /// The elements of the `references` array have the following fields:
 /// - `key`: the original bibliography key (without Alexandria's prefix).
+/// - `aliases`: further keys by which the entry can be cited, e.g. those listed in its `ids` or
+///   those of merged entries.
 /// - `content`: a Typst representation of the bibliographical entry; used by
 ///   @@render-bibliography() for rendering bibliographical items.
 /// - optional `first-field`: Typst content for certain bibliography styles. For example,
@@ -271,6 +431,86 @@
   get-bibliography(prefix)
 }
 
+/// Checks the entries cited for a given prefix against the variables the style uses, without
+/// loading the bibliography for rendering, e.g. to find entries without a date, which the style
+/// would print as "n.d.", or articles without a journal:
+/// ```typ
+/// #context lint-bibliography("bibliography.bib", style: "apa")
+/// ```
+/// The result is an array of diagnostics like those in the `diagnostics` of @@get-bibliography(),
+/// with a `severity`, a `message`, the `key` of the entry and, where known, the `path`, `line`
+/// and `column` in its file. Passing `lint: true` to @@load-bibliography() reports the same
+/// problems together with the other diagnostics.
+///
+/// This function is contextual.
+///
+/// -> array
+#let lint-bibliography(
+  /// the path(s) to the bibliography file(s), or their contents or entries, see
+  /// @@load-bibliography().
+  /// -> string | bytes | dictionary | array
+  path,
+  /// the optional prefix whose citations are checked.
+  /// It only needs to be specified if more than one prefix was registered.
+  /// -> string | auto
+  prefix: auto,
+  /// whether to check all entries from `path` or only the ones cited in the document.
+  /// -> boolean
+  full: false,
+  /// the keys of further entries to check without citing them, see @@load-bibliography().
+  /// -> array
+  nocite: (),
+  /// the style whose variables the entries are checked against, see @@load-bibliography().
+  /// -> string | bytes
+  style: "ieee",
+  /// the citation keys of entries identified by their DOI, see @@load-bibliography().
+  /// -> dictionary
+  doi-keys: (:),
+  /// the format of the bibliography file(s), see @@load-bibliography().
+  /// -> string | auto
+  format: auto,
+  /// the text encoding of the bibliography file(s), see @@load-bibliography().
+  /// -> string | auto
+  encoding: auto,
+  /// how to handle keys that appear in more than one bibliography file, see
+  /// @@load-bibliography().
+  /// -> string
+  duplicates: "error",
+  /// whether to merge entries describing the same work, see @@load-bibliography().
+  /// -> boolean
+  deduplicate: false,
+  /// whether citation keys are looked up ignoring their case, see @@load-bibliography().
+  /// -> boolean
+  case-insensitive-keys: false,
+  /// whether to skip invalid BibLaTeX entries instead of failing, see @@load-bibliography().
+  /// -> boolean
+  skip-invalid: false,
+) = {
+  import "state.typ": *
+  import "internal.typ": *
+
+  let prefix = prefix
+  if prefix == auto {
+    prefix = get-only-prefix()
+    assert.ne(prefix, none, message: "when using multiple custom bibliographies, you must specify the prefix for each")
+  }
+
+  let config = plugin-config(
+    path,
+    style,
+    format: format,
+    encoding: encoding,
+    full: full,
+    nocite: nocite,
+    doi-keys: doi-keys,
+    duplicates: duplicates,
+    deduplicate: deduplicate,
+    case-insensitive-keys: case-insensitive-keys,
+    skip-invalid: skip-invalid,
+  )
+  hayagriva.lint(config(get-citations(prefix)))
+}
+
 /// Renders the given list of bibliographical references.
 /// For simple use cases, @@bibliographyx() can be called directly.
 ///
@@ -311,7 +551,9 @@
       ..for e in bib.references {
         (
           {
-            [#metadata(none)#label(bib.prefix + e.key)]
+            for key in (e.key, ..e.aliases) {
+              [#metadata(none)#label(bib.prefix + key)]
+            }
             if e.first-field != none {
               hayagriva.render(e.first-field)
             }
@@ -324,7 +566,9 @@
     let gutter = v(par.spacing, weak: true)
     for (i, e) in bib.references.enumerate() {
       if i != 0 { gutter }
-      [#metadata(none)#label(bib.prefix + e.key)]
+      for key in (e.key, ..e.aliases) {
+        [#metadata(none)#label(bib.prefix + key)]
+      }
       hayagriva.render(e.content)
     }
   }
@@ -352,8 +596,9 @@
 /// -> content
 #let bibliographyx(
   /// the path(s) to the bibliography file(s), which is passed to the `read` function
-  /// registered via @@alexandria(), or its binary contents if no `read` function provided.
-  /// -> string | bytes | array
+  /// registered via @@alexandria(), or its binary contents if no `read` function provided, or
+  /// entries given directly as a dictionary; see @@load-bibliography().
+  /// -> string | bytes | dictionary | array
   path,
   /// the optional prefix for which the bibliography is generated.
   /// It only needs to be specified if more than one prefix was registered.
@@ -366,13 +611,60 @@
   /// cited in the document.
   /// -> boolean
   full: false,
+  /// the keys of further entries to include without citing them, see @@load-bibliography().
+  /// -> array
+  nocite: (),
   /// the style of the bibliography. Either a #link("https://typst.app/docs/reference/model/bibliography/#parameters-style")[built-in style],
   /// a path to a CSL file passed to `read` registered via @@alexandria(), or its binary
   /// contents.
   /// -> string | bytes
   style: "ieee",
+  /// the citation keys of entries identified by their DOI, see @@load-bibliography().
+  /// -> dictionary
+  doi-keys: (:),
+  /// the format of the bibliography file(s), see @@load-bibliography().
+  /// -> string | auto
+  format: auto,
+  /// the text encoding of the bibliography file(s), see @@load-bibliography().
+  /// -> string | auto
+  encoding: auto,
+  /// how to handle keys that appear in more than one bibliography file, see
+  /// @@load-bibliography().
+  /// -> string
+  duplicates: "error",
+  /// whether to merge entries describing the same work, see @@load-bibliography().
+  /// -> boolean
+  deduplicate: false,
+  /// whether citation keys are looked up ignoring their case, see @@load-bibliography().
+  /// -> boolean
+  case-insensitive-keys: false,
+  /// how to handle citations of keys that are not in the bibliography, see @@load-bibliography().
+  /// -> string
+  missing: "error",
+  /// whether to skip invalid BibLaTeX entries instead of failing, see @@load-bibliography().
+  /// -> boolean
+  skip-invalid: false,
+  /// whether to check the cited entries against the variables the style uses, see
+  /// @@load-bibliography().
+  /// -> boolean
+  lint: false,
 ) = {
-  load-bibliography(path, prefix: prefix, full: full, style: style)
+  load-bibliography(
+    path,
+    prefix: prefix,
+    full: full,
+    nocite: nocite,
+    style: style,
+    doi-keys: doi-keys,
+    format: format,
+    encoding: encoding,
+    duplicates: duplicates,
+    deduplicate: deduplicate,
+    case-insensitive-keys: case-insensitive-keys,
+    missing: missing,
+    skip-invalid: skip-invalid,
+    lint: lint,
+  )
 
   context {
     let bib = get-bibliography(prefix)
@@ -12,7 +12,8 @@
 
 #let read(data) = {
   if type(data) == bytes {
-    (path: none, data: str(data))
+    // the plugin detects the encoding of the raw bytes
+    (path: none, data: data)
   } else if type(data) == str {
     let read = config.get().read
     assert.ne(read, none, message: "Alexandria is not configured. Make sure to use `#show: alexandria(...)`")
@@ -112,6 +113,7 @@
 }
 
 #let get-bibliography(prefix) = bibliographies.final().at(prefix)
+#let get-citations(prefix) = config.final().prefixes.at(prefix).citations
 #let get-citation(prefix, index) = {
   let body = get-bibliography(prefix).citations.at(index)
   let supplements = config.final().prefixes.at(prefix).citations.at(index)
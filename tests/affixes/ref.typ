@@ -0,0 +1,9 @@
+#import "../test-utils.typ": *
+
+[see 1, for a review] #cite(<netwok>, form: none)
+
+[cf. 2, p. 5] #cite(<glacier-melt>, form: none)
+
+#bib(
+  // title: "Bibliography",
+)
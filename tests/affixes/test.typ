@@ -0,0 +1,11 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+#citex(<x:netwok>, prefix: "see", suffix: ", for a review")
+
+#citex(<x:glacier-melt>, prefix: [cf.], supplement: [p. 5])
+
+#bibliographyx(
+  "../bibliography.bib",
+  title: "Bibliography",
+)
@@ -0,0 +1,8 @@
+#import "../test-utils.typ": *
+
+@mueller
+
+#bibliography(
+  "refs.bib",
+  // title: "Bibliography",
+)
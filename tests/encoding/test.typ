@@ -0,0 +1,11 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+@x:mueller
+
+// the raw bytes of a file that is not UTF-8
+#bibliographyx(
+  read("refs-latin1.bib", encoding: none),
+  encoding: "latin-1",
+  title: "Bibliography",
+)
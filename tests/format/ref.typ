@@ -0,0 +1,7 @@
+#import "../test-utils.typ": *
+
+@netwok @glacier-melt
+
+#bib(
+  // title: "Bibliography",
+)
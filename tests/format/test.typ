@@ -0,0 +1,11 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+@x:netwok @x:glacier-melt
+
+// the extension doesn't tell the format
+#bibliographyx(
+  "refs.txt",
+  format: "biblatex",
+  title: "Bibliography",
+)
@@ -0,0 +1,8 @@
+#import "../test-utils.typ": *
+
+@memo
+
+#bibliography(
+  "refs.yaml",
+  // title: "Bibliography",
+)
@@ -0,0 +1,9 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+@x:memo
+
+#bibliographyx(
+  (memo: (type: "report", title: "Internal memo", author: "Astley, Rick", date: "2024-05")),
+  title: "Bibliography",
+)
@@ -0,0 +1,13 @@
+#import "../test-utils.typ": *
+
+#cite(<dumplings>, form: none)
+
+#"entry `dumplings` has no `container-title`, which the style uses"
+
+#"entry `dumplings` has no `issued`, which the style uses"
+
+#bibliography(
+  "refs.bib",
+  // title: "Bibliography",
+  style: "apa",
+)
@@ -0,0 +1,15 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+#cite(<x:dumplings>, form: none)
+
+#context for diagnostic in alexandria.lint-bibliography("refs.bib", style: "apa") [
+  #diagnostic.message
+
+]
+
+#bibliographyx(
+  "refs.bib",
+  title: "Bibliography",
+  style: "apa",
+)
@@ -0,0 +1,9 @@
+#import "../test-utils.typ": *
+
+@netwok[p. 5]
+
+@netwok[p. 7]
+
+#bib(
+  // title: "Bibliography",
+)
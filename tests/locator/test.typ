@@ -0,0 +1,13 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+// a supplement that is parsed into a locator
+@x:netwok[p. 5]
+
+// an explicit locator
+@x:netwok[#locator("page", "7")]
+
+#bibliographyx(
+  "../bibliography.bib",
+  title: "Bibliography",
+)
@@ -0,0 +1,7 @@
+#import "../test-utils.typ": *
+
+@netwok #cite(<glacier-melt>, form: none) #cite(<restful>, form: none)
+
+#bib(
+  // title: "Bibliography",
+)
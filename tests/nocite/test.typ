@@ -0,0 +1,10 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+@x:netwok
+
+#bibliographyx(
+  "../bibliography.bib",
+  title: "Bibliography",
+  nocite: ("glacier-melt", "restful"),
+)
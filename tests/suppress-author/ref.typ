@@ -0,0 +1,8 @@
+#import "../test-utils.typ": *
+
+Astley and Morris argue (2020, p. 4). #cite(<netwok>, form: none)
+
+#bib(
+  // title: "Bibliography",
+  style: "apa",
+)
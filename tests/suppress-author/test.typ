@@ -0,0 +1,10 @@
+#import "../test-utils.typ": *
+#show: alexandria.alexandria(prefix: "x:", read: path => read(path))
+
+Astley and Morris argue #citex(<x:netwok>, form: "suppress-author", supplement: [p. 4]).
+
+#bibliographyx(
+  "../bibliography.bib",
+  title: "Bibliography",
+  style: "apa",
+)